@@ -0,0 +1,217 @@
+//! Memoized extraction for ranim items.
+//!
+//! [`CachedExtract`] wraps an item and keeps the result of its last [`Extract::extract`], the
+//! item can only be mutated through [`std::ops::DerefMut`] which drops the cache, so a stale
+//! result is never returned.
+
+use std::{
+    ops::{Deref, DerefMut, Range},
+    sync::{Arc, OnceLock},
+};
+
+use ranim::{
+    prelude::{Alignable, Interpolatable, Partial},
+    render::primitives::Extract,
+    traits::Empty,
+};
+
+pub struct CachedExtract<T: Extract> {
+    inner: T,
+    /// Shared between the clones until one of them is mutated
+    cache: OnceLock<Arc<T::Target>>,
+}
+
+impl<T: Extract> CachedExtract<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            cache: OnceLock::new(),
+        }
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    /// Whether the next extraction will reuse the cache.
+    pub fn is_cached(&self) -> bool {
+        self.cache.get().is_some()
+    }
+    /// Drop the cache, which is only needed if the item reads state from outside of itself.
+    pub fn invalidate(&mut self) {
+        self.cache.take();
+    }
+}
+
+impl<T: Extract + Clone> Clone for CachedExtract<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<T: Extract + Default> Default for CachedExtract<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Extract> From<T> for CachedExtract<T> {
+    fn from(inner: T) -> Self {
+        Self::new(inner)
+    }
+}
+
+impl<T: Extract> Deref for CachedExtract<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T: Extract> DerefMut for CachedExtract<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.invalidate();
+        &mut self.inner
+    }
+}
+
+impl<T: Extract> Extract for CachedExtract<T>
+where
+    T::Target: Clone,
+{
+    type Target = T::Target;
+    fn extract(&self) -> Self::Target {
+        self.cache
+            .get_or_init(|| Arc::new(self.inner.extract()))
+            .as_ref()
+            .clone()
+    }
+}
+
+// MARK: Anim traits
+// The results are new items, so they start without cache.
+
+impl<T: Extract + Interpolatable> Interpolatable for CachedExtract<T> {
+    fn lerp(&self, target: &Self, t: f64) -> Self {
+        Self::new(self.inner.lerp(&target.inner, t))
+    }
+}
+
+impl<T: Extract + Alignable> Alignable for CachedExtract<T> {
+    fn is_aligned(&self, other: &Self) -> bool {
+        self.inner.is_aligned(&other.inner)
+    }
+    fn align_with(&mut self, other: &mut Self) {
+        self.deref_mut().align_with(other.deref_mut());
+    }
+}
+
+impl<T: Extract + Partial> Partial for CachedExtract<T> {
+    fn get_partial(&self, range: Range<f64>) -> Self {
+        Self::new(self.inner.get_partial(range))
+    }
+    fn get_partial_closed(&self, range: Range<f64>) -> Self {
+        Self::new(self.inner.get_partial_closed(range))
+    }
+}
+
+impl<T: Extract + Empty> Empty for CachedExtract<T> {
+    fn empty() -> Self {
+        Self::new(T::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rayon::prelude::*;
+
+    use super::*;
+
+    /// Counts its extractions, which tells a cache hit from a miss.
+    #[derive(Clone, Default)]
+    struct Counted {
+        value: u32,
+        extractions: Arc<AtomicUsize>,
+    }
+
+    impl Counted {
+        fn new(value: u32) -> Self {
+            Self {
+                value,
+                ..Default::default()
+            }
+        }
+        fn extractions(&self) -> usize {
+            self.extractions.load(Ordering::Relaxed)
+        }
+    }
+
+    impl Extract for Counted {
+        type Target = Vec<u32>;
+        fn extract(&self) -> Self::Target {
+            self.extractions.fetch_add(1, Ordering::Relaxed);
+            vec![self.value]
+        }
+    }
+
+    #[test]
+    fn mutation_drops_the_cache() {
+        let mut item = CachedExtract::new(Counted::new(0));
+        assert!(!item.is_cached());
+        assert_eq!(item.extract(), vec![0]);
+        assert_eq!(item.extract(), vec![0]);
+        assert_eq!(item.extractions(), 1);
+
+        item.value = 1;
+        assert!(!item.is_cached());
+        assert_eq!(item.extract(), vec![1]);
+        assert_eq!(item.extractions(), 2);
+    }
+
+    #[test]
+    fn clones_share_the_cache_until_mutated() {
+        let item = CachedExtract::new(Counted::new(0));
+        item.extract();
+        let mut clone = item.clone();
+        assert!(Arc::ptr_eq(
+            item.cache.get().unwrap(),
+            clone.cache.get().unwrap()
+        ));
+        assert_eq!(clone.extract(), vec![0]);
+        assert_eq!(item.extractions(), 1);
+
+        clone.value = 2;
+        assert_eq!(clone.extract(), vec![2]);
+        assert_eq!(item.extract(), vec![0]);
+        assert_eq!(item.extractions(), 2);
+    }
+
+    #[test]
+    fn par_iter() {
+        let mut items = (0..64)
+            .map(|value| CachedExtract::new(Counted::new(value)))
+            .collect::<Vec<_>>();
+        let extracted = items.par_iter().map(Extract::extract).collect::<Vec<_>>();
+        assert!(
+            extracted
+                .iter()
+                .zip(0..)
+                .all(|(item, value)| *item == [value])
+        );
+
+        items
+            .par_iter_mut()
+            .filter(|item| item.value % 2 == 0)
+            .for_each(|item| item.value += 100);
+        let extracted = items.par_iter().map(Extract::extract).collect::<Vec<_>>();
+        for (value, (item, extracted)) in (0..).zip(items.iter().zip(extracted)) {
+            let mutated = value % 2 == 0;
+            let expected = if mutated { value + 100 } else { value };
+            assert_eq!(extracted, [expected]);
+            assert_eq!(item.extractions(), 1 + mutated as usize);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use ranim::{
+    color::palettes::manim,
+    glam::{DVec3, dvec3},
+    items::vitem::{VItem, geometry::Circle},
+    prelude::*,
+    render::primitives::{Extract, vitem::VItemPrimitive},
+};
+
+use crate::{
+    colormap::Colormap,
+    event::{Event, Polarity},
+    glyph::glyph_label,
+};
+
+/// How the dots of an [`EventCloud`] are colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudColoring {
+    /// ON events are yellow and OFF events are blue.
+    Polarity,
+    /// From the oldest event at 0.0 to the newest one at 1.0 of the colormap.
+    Age(Colormap),
+}
+
+/// The spatio-temporal point cloud of an event stream.
+///
+/// The sensor x goes along the scene x, the sensor y goes down along the scene y like the
+/// [`crate::TimeSurface`], and the time goes along the scene z towards the viewer of the
+/// default camera, so the cloud looks like a flat frame until the camera orbits around it.
+#[derive(Clone)]
+pub struct EventCloud {
+    pub events: Vec<Event>,
+    /// The sensor size in pixels
+    pub width: usize,
+    pub height: usize,
+    pub center: DVec3,
+    /// The size of the box that the cloud fills, `z` is the length of the time axis
+    pub size: DVec3,
+    pub coloring: CloudColoring,
+    pub dot_radius: f64,
+    /// The normal of the dots, keep it towards the camera so they don't get flattened
+    pub dot_facing: DVec3,
+    pub show_axes: bool,
+    pub label_size: f64,
+}
+
+impl EventCloud {
+    pub fn new(events: Vec<Event>, width: usize, height: usize) -> Self {
+        let aspect = width as f64 / height.max(1) as f64;
+        Self {
+            events,
+            width,
+            height,
+            center: DVec3::ZERO,
+            size: dvec3(4.0 * aspect, 4.0, 6.0),
+            coloring: CloudColoring::Polarity,
+            dot_radius: 0.03,
+            dot_facing: DVec3::Z,
+            show_axes: true,
+            label_size: 0.3,
+        }
+    }
+    pub fn set_coloring(&mut self, coloring: CloudColoring) -> &mut Self {
+        self.coloring = coloring;
+        self
+    }
+    pub fn set_size(&mut self, size: DVec3) -> &mut Self {
+        self.size = size;
+        self
+    }
+    /// Face the dots towards the camera looking at `facing`.
+    pub fn face_camera(&mut self, facing: DVec3) -> &mut Self {
+        self.dot_facing = -facing.normalize_or(DVec3::NEG_Z);
+        self
+    }
+    fn time_range(&self) -> (u64, u64) {
+        self.events
+            .iter()
+            .fold((u64::MAX, 0), |(min_t, max_t), event| {
+                (min_t.min(event.t), max_t.max(event.t))
+            })
+    }
+    /// The corner at pixel `(0, 0)` and the earliest time.
+    fn origin(&self) -> DVec3 {
+        self.center + dvec3(-self.size.x, self.size.y, -self.size.z) / 2.0
+    }
+    /// The position of a pixel at a normalized time in [0.0, 1.0].
+    fn pos_of(&self, x: f64, y: f64, t: f64) -> DVec3 {
+        let (width, height) = (self.width.max(1) as f64, self.height.max(1) as f64);
+        self.origin()
+            + dvec3(
+                (x + 0.5) / width * self.size.x,
+                -(y + 0.5) / height * self.size.y,
+                t * self.size.z,
+            )
+    }
+    /// The position and the color of the dot of each event.
+    ///
+    /// The earliest event is at the back of the time axis and the latest one at the front, a
+    /// stream whose events all have the same time is at the back.
+    fn dots(&self) -> impl Iterator<Item = (DVec3, color::AlphaColor<color::Srgb>)> + '_ {
+        let (min_t, max_t) = self.time_range();
+        let span = max_t.saturating_sub(min_t).max(1) as f64;
+        self.events.iter().map(move |event| {
+            let age = (event.t.saturating_sub(min_t)) as f64 / span;
+            let color = match self.coloring {
+                CloudColoring::Polarity => match event.polarity {
+                    Polarity::On => manim::YELLOW_C,
+                    Polarity::Off => manim::BLUE_C,
+                },
+                CloudColoring::Age(colormap) => colormap.sample(age as f32),
+            };
+            (self.pos_of(event.x as f64, event.y as f64, age), color)
+        })
+    }
+    fn dot(&self, pos: DVec3, color: color::AlphaColor<color::Srgb>) -> VItem {
+        let facing = self.dot_facing.normalize_or(DVec3::Z);
+        let axis = DVec3::Z.cross(facing);
+        let angle = DVec3::Z.angle_between(facing);
+        VItem::from(Circle::new(self.dot_radius).with(|circle| {
+            circle
+                .set_fill_color(color)
+                .set_stroke_color(color.with_alpha(0.0));
+        }))
+        .with(|dot| {
+            if axis.length_squared() > 1e-12 {
+                dot.rotate(angle, axis.normalize());
+            } else if angle > 1.0 {
+                // Facing -z, flip around any axis perpendicular to z.
+                dot.rotate(angle, DVec3::X);
+            }
+            dot.put_center_on(pos);
+        })
+    }
+    fn axes(&self) -> Vec<VItem> {
+        let origin = self.origin();
+        let axes = [
+            (dvec3(self.size.x, 0.0, 0.0), "x"),
+            (dvec3(0.0, -self.size.y, 0.0), "y"),
+            (dvec3(0.0, 0.0, self.size.z), "t"),
+        ];
+        axes.into_iter()
+            .flat_map(|(axis, label)| {
+                let end = origin + axis;
+                let line =
+                    VItem::from_vpoints(vec![origin, (origin + end) / 2.0, end]).with(|line| {
+                        line.set_stroke_color(manim::WHITE).set_stroke_width(0.02);
+                    });
+                let text = glyph_label(
+                    label,
+                    self.label_size,
+                    end + axis.normalize() * self.label_size,
+                )
+                .with(|text| {
+                    text.set_fill_color(manim::WHITE);
+                });
+                std::iter::once(line).chain(text)
+            })
+            .collect()
+    }
+}
+
+impl BoundingBox for EventCloud {
+    fn get_bounding_box(&self) -> [DVec3; 3] {
+        let half = self.size / 2.0;
+        [self.center - half, self.center, self.center + half]
+    }
+}
+
+impl Shift for EventCloud {
+    fn shift(&mut self, shift: DVec3) -> &mut Self {
+        self.center += shift;
+        self
+    }
+}
+
+impl Extract for EventCloud {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let dots = self.dots().map(|(pos, color)| self.dot(pos, color));
+        let axes = if self.show_axes { self.axes() } else { vec![] };
+        axes.into_iter()
+            .chain(dots)
+            .map(|item| item.extract())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cloud(events: Vec<Event>) -> EventCloud {
+        let mut cloud = EventCloud::new(events, 4, 2);
+        cloud.set_size(dvec3(8.0, 4.0, 10.0));
+        cloud.shift(dvec3(1.0, 2.0, 3.0));
+        cloud
+    }
+
+    fn positions(cloud: &EventCloud) -> Vec<DVec3> {
+        cloud.dots().map(|(pos, _)| pos).collect()
+    }
+
+    #[test]
+    fn time_goes_along_the_depth() {
+        let cloud = cloud(vec![
+            Event::new(100, 0, 0, Polarity::On),
+            Event::new(150, 0, 0, Polarity::On),
+            Event::new(200, 0, 0, Polarity::On),
+        ]);
+        let [first, middle, last] = positions(&cloud)[..] else {
+            panic!("expected 3 dots");
+        };
+        // The box is 10 deep around z = 3
+        assert_eq!(first.z, -2.0);
+        assert_eq!(middle.z, 3.0);
+        assert_eq!(last.z, 8.0);
+        assert_eq!((first.x, first.y), (middle.x, middle.y));
+        assert_eq!((first.x, first.y), (last.x, last.y));
+    }
+
+    #[test]
+    fn y_points_down() {
+        let cloud = cloud(vec![
+            Event::new(0, 0, 0, Polarity::On),
+            Event::new(0, 3, 0, Polarity::On),
+            Event::new(0, 0, 1, Polarity::On),
+        ]);
+        let [top_left, top_right, bottom_left] = positions(&cloud)[..] else {
+            panic!("expected 3 dots");
+        };
+        // Pixels of 2 x 2 in the 8 x 4 box around (1, 2)
+        assert_eq!((top_left.x, top_left.y), (-2.0, 3.0));
+        assert_eq!((top_right.x, top_right.y), (4.0, 3.0));
+        assert_eq!((bottom_left.x, bottom_left.y), (-2.0, 1.0));
+    }
+
+    #[test]
+    fn single_time_does_not_divide_by_zero() {
+        let cloud = cloud(vec![
+            Event::new(42, 1, 1, Polarity::On),
+            Event::new(42, 2, 0, Polarity::Off),
+        ]);
+        for pos in positions(&cloud) {
+            assert!(pos.is_finite());
+            assert_eq!(pos.z, -2.0);
+        }
+        assert!(positions(&EventCloud::new(vec![], 4, 2)).is_empty());
+    }
+
+    #[test]
+    fn colors_by_polarity_and_age() {
+        let events = vec![
+            Event::new(0, 0, 0, Polarity::Off),
+            Event::new(50, 1, 0, Polarity::On),
+            Event::new(100, 2, 0, Polarity::Off),
+        ];
+        let mut cloud = cloud(events);
+        let colors = |cloud: &EventCloud| cloud.dots().map(|(_, color)| color).collect::<Vec<_>>();
+        assert_eq!(
+            colors(&cloud),
+            [manim::BLUE_C, manim::YELLOW_C, manim::BLUE_C]
+        );
+
+        cloud.set_coloring(CloudColoring::Age(Colormap::Viridis));
+        assert_eq!(
+            colors(&cloud),
+            [0.0, 0.5, 1.0].map(|age| Colormap::Viridis.sample(age))
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use ranim::{
+    color::palettes::manim,
+    glam::{DVec3, dvec3},
+    items::vitem::{VItem, geometry::Rectangle},
+    prelude::*,
+    render::primitives::{Extract, vitem::VItemPrimitive},
+};
+
+use crate::glyph::glyph_label;
+
+/// Colormaps for turning a normalized value in [0.0, 1.0] into a color.
+///
+/// The sequential maps are the matplotlib ones, the diverging maps have their neutral color
+/// at 0.5 and are meant for polarity: OFF below 0.5 and ON above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    Viridis,
+    Inferno,
+    Magma,
+    Turbo,
+    CoolWarm,
+    RdBu,
+}
+
+const VIRIDIS: [u32; 9] = [
+    0x440154, 0x472d7b, 0x3b528b, 0x2c728e, 0x21918c, 0x28ae80, 0x5ec962, 0xaddc30, 0xfde725,
+];
+const INFERNO: [u32; 9] = [
+    0x000004, 0x1f0c48, 0x550f6d, 0x88226a, 0xba3655, 0xe35933, 0xf98e09, 0xf8c932, 0xfcffa4,
+];
+const MAGMA: [u32; 9] = [
+    0x000004, 0x1c1044, 0x4f127b, 0x812581, 0xb5367a, 0xe55064, 0xfb8761, 0xfec287, 0xfcfdbf,
+];
+const COOL_WARM: [u32; 9] = [
+    0x3b4cc0, 0x6282ea, 0x8db0fe, 0xb8d0f9, 0xdddddd, 0xf5c4ad, 0xf49a7b, 0xde604d, 0xb40426,
+];
+// ColorBrewer RdBu, reversed so that blue is at 0.0.
+const RD_BU: [u32; 9] = [
+    0x2166ac, 0x4393c3, 0x92c5de, 0xd1e5f0, 0xf7f7f7, 0xfddbc7, 0xf4a582, 0xd6604d, 0xb2182b,
+];
+
+fn hex(hex: u32) -> [f32; 3] {
+    [
+        ((hex >> 16) & 0xff) as f32 / 255.0,
+        ((hex >> 8) & 0xff) as f32 / 255.0,
+        (hex & 0xff) as f32 / 255.0,
+    ]
+}
+
+/// Linear interpolation between evenly spaced stops.
+fn sample_stops(stops: &[u32], t: f32) -> [f32; 3] {
+    let pos = t * (stops.len() - 1) as f32;
+    let idx = (pos.floor() as usize).min(stops.len() - 2);
+    let frac = pos - idx as f32;
+    let (a, b) = (hex(stops[idx]), hex(stops[idx + 1]));
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * frac)
+}
+
+/// The polynomial approximation of Google's Turbo.
+fn sample_turbo(t: f32) -> [f32; 3] {
+    let t = t as f64;
+    let r = 0.13572138
+        + t * (4.61539260
+            + t * (-42.66032258 + t * (132.13108234 + t * (-152.94239396 + t * 59.28637943))));
+    let g = 0.09140261
+        + t * (2.19418839
+            + t * (4.84296658 + t * (-14.18503333 + t * (4.27729857 + t * 2.82956604))));
+    let b = 0.10667330
+        + t * (12.64194608
+            + t * (-60.58204836 + t * (110.36276771 + t * (-89.90310912 + t * 27.34824973))));
+    [r, g, b].map(|c| c.clamp(0.0, 1.0) as f32)
+}
+
+impl Colormap {
+    /// Sample the colormap, `t` is clamped into [0.0, 1.0] and NaN is treated as 0.0.
+    pub fn sample(&self, t: f32) -> color::AlphaColor<color::Srgb> {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let [r, g, b] = match self {
+            Colormap::Viridis => sample_stops(&VIRIDIS, t),
+            Colormap::Inferno => sample_stops(&INFERNO, t),
+            Colormap::Magma => sample_stops(&MAGMA, t),
+            Colormap::Turbo => sample_turbo(t),
+            Colormap::CoolWarm => sample_stops(&COOL_WARM, t),
+            Colormap::RdBu => sample_stops(&RD_BU, t),
+        };
+        rgb(r, g, b)
+    }
+    pub fn is_diverging(&self) -> bool {
+        matches!(self, Colormap::CoolWarm | Colormap::RdBu)
+    }
+}
+
+/// A vertical colorbar with tick labels on its right side.
+#[derive(Clone)]
+pub struct Colorbar {
+    pub colormap: Colormap,
+    pub center: DVec3,
+    pub width: f64,
+    pub height: f64,
+    /// `(value in [0.0, 1.0], label)`, 0.0 is at the bottom
+    pub ticks: Vec<(f32, String)>,
+    /// The digit height of the tick labels
+    pub label_size: f64,
+    /// The count of the color segments
+    pub segments: usize,
+}
+
+impl Colorbar {
+    pub fn new(colormap: Colormap, width: f64, height: f64) -> Self {
+        Self {
+            colormap,
+            center: DVec3::ZERO,
+            width,
+            height,
+            ticks: vec![],
+            label_size: 0.2,
+            segments: 64,
+        }
+    }
+    pub fn set_ticks(&mut self, ticks: Vec<(f32, String)>) -> &mut Self {
+        self.ticks = ticks;
+        self
+    }
+    /// `cnt` evenly spaced ticks labelled from `min` to `max`, with as many decimals as the
+    /// step between the ticks needs.
+    pub fn set_linear_ticks(&mut self, min: f64, max: f64, cnt: usize) -> &mut Self {
+        let step = (max - min).abs() / (cnt.max(2) - 1) as f64;
+        // The decimals of the magnitude of the step, and up to two more if they make it exact,
+        // so that 0.25 isn't printed as 0.2.
+        let least = if step > 0.0 {
+            (-step.log10().floor()).clamp(0.0, 6.0) as usize
+        } else {
+            0
+        };
+        let decimals = (least..=least + 2)
+            .find(|&decimals| {
+                let scaled = step * 10f64.powi(decimals as i32);
+                (scaled - scaled.round()).abs() < 1e-6 * scaled
+            })
+            .unwrap_or(least);
+        let ticks = (0..cnt)
+            .map(|i| {
+                let t = i as f64 / (cnt.max(2) - 1) as f64;
+                let label = format!("{:.*}", decimals, min + (max - min) * t);
+                // Values that round to zero would be printed as "-0".
+                let label = match label.strip_prefix('-') {
+                    Some(abs) if abs.chars().all(|c| matches!(c, '0' | '.')) => abs.to_string(),
+                    _ => label,
+                };
+                (t as f32, label)
+            })
+            .collect();
+        self.set_ticks(ticks)
+    }
+    fn value_pos(&self, t: f32) -> DVec3 {
+        self.center + dvec3(0.0, (t as f64 - 0.5) * self.height, 0.0)
+    }
+}
+
+impl BoundingBox for Colorbar {
+    fn get_bounding_box(&self) -> [DVec3; 3] {
+        let half = dvec3(self.width, self.height, 0.0) / 2.0;
+        [self.center - half, self.center, self.center + half]
+    }
+}
+
+impl Shift for Colorbar {
+    fn shift(&mut self, shift: DVec3) -> &mut Self {
+        self.center += shift;
+        self
+    }
+}
+
+impl Extract for Colorbar {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let segment_height = self.height / self.segments as f64;
+        let segments = (0..self.segments).map(|i| {
+            let t = (i as f32 + 0.5) / self.segments as f32;
+            let color = self.colormap.sample(t);
+            // Slightly overlapping to avoid seams between segments
+            VItem::from(
+                Rectangle::new(self.width, segment_height * 1.05).with(|rect| {
+                    rect.set_fill_color(color)
+                        .set_stroke_color(color.with_alpha(0.0))
+                        .put_center_on(self.value_pos(t));
+                }),
+            )
+        });
+        let outline = VItem::from(Rectangle::new(self.width, self.height).with(|rect| {
+            rect.set_stroke_color(manim::WHITE)
+                .put_center_on(self.center);
+        }));
+        let ticks = self.ticks.iter().flat_map(|(t, label)| {
+            let right = self.value_pos(*t) + DVec3::X * self.width / 2.0;
+            let tick = VItem::from_vpoints(vec![
+                right,
+                right + DVec3::X * self.width * 0.15,
+                right + DVec3::X * self.width * 0.3,
+            ])
+            .with(|line| {
+                line.set_stroke_color(manim::WHITE).set_stroke_width(0.015);
+            });
+            // Left aligned on the tick, the label is centered on `left` at first.
+            let left = right + DVec3::X * (self.width * 0.3 + self.label_size * 0.5);
+            let text = glyph_label(label, self.label_size, left).with(|text| {
+                if !text.is_empty() {
+                    let [min, _, _] = text.get_bounding_box();
+                    text.shift(DVec3::X * (left.x - min.x))
+                        .set_fill_color(manim::WHITE);
+                }
+            });
+            std::iter::once(tick).chain(text)
+        });
+        segments
+            .chain([outline])
+            .chain(ticks)
+            .map(|item| item.extract())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Colormap; 6] = [
+        Colormap::Viridis,
+        Colormap::Inferno,
+        Colormap::Magma,
+        Colormap::Turbo,
+        Colormap::CoolWarm,
+        Colormap::RdBu,
+    ];
+
+    fn labels(min: f64, max: f64, cnt: usize) -> Vec<String> {
+        let mut colorbar = Colorbar::new(Colormap::Viridis, 0.2, 2.0);
+        colorbar.set_linear_ticks(min, max, cnt);
+        colorbar
+            .ticks
+            .iter()
+            .map(|(_, label)| label.clone())
+            .collect()
+    }
+
+    #[test]
+    fn sample_hits_the_end_stops() {
+        let stop = |hex_color| {
+            let [r, g, b] = hex(hex_color);
+            rgb(r, g, b)
+        };
+        assert_eq!(Colormap::Viridis.sample(0.0), stop(VIRIDIS[0]));
+        assert_eq!(Colormap::Inferno.sample(1.0), stop(INFERNO[8]));
+        assert_eq!(Colormap::CoolWarm.sample(0.5), stop(COOL_WARM[4]));
+    }
+
+    #[test]
+    fn sample_clamps_out_of_range_and_nan() {
+        for colormap in ALL {
+            assert_eq!(colormap.sample(-3.0), colormap.sample(0.0), "{colormap:?}");
+            assert_eq!(
+                colormap.sample(f32::NEG_INFINITY),
+                colormap.sample(0.0),
+                "{colormap:?}"
+            );
+            assert_eq!(colormap.sample(7.0), colormap.sample(1.0), "{colormap:?}");
+            assert_eq!(
+                colormap.sample(f32::INFINITY),
+                colormap.sample(1.0),
+                "{colormap:?}"
+            );
+            assert_eq!(
+                colormap.sample(f32::NAN),
+                colormap.sample(0.0),
+                "{colormap:?}"
+            );
+            for t in [0.0, 0.3, 1.0] {
+                let color = colormap.sample(t);
+                assert!(
+                    color.components.iter().all(|c| (0.0..=1.0).contains(c)),
+                    "{colormap:?} at {t}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn linear_ticks_have_the_precision_of_their_step() {
+        assert_eq!(labels(0.0, 100.0, 3), ["0", "50", "100"]);
+        assert_eq!(
+            labels(0.0, 1.0, 5),
+            ["0.00", "0.25", "0.50", "0.75", "1.00"]
+        );
+        assert_eq!(labels(0.0, 1.0, 3), ["0.0", "0.5", "1.0"]);
+        assert_eq!(labels(0.0, 1.0, 4), ["0.0", "0.3", "0.7", "1.0"]);
+        assert_eq!(labels(-0.5, 0.5, 3), ["-0.5", "0.0", "0.5"]);
+        assert_eq!(labels(10.0, 0.0, 2), ["10", "0"]);
+        assert_eq!(labels(3.0, 3.0, 2), ["3", "3"]);
+    }
+
+    #[test]
+    fn linear_ticks_have_no_negative_zero() {
+        assert_eq!(labels(-1.0, 1.0, 3), ["-1", "0", "1"]);
+        assert_eq!(labels(-1e-9, 2.0, 3), ["0", "1", "2"]);
+        assert_eq!(labels(-0.001, 0.0, 2), ["-0.001", "0.000"]);
+    }
+}
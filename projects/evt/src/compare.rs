@@ -0,0 +1,283 @@
+use std::sync::Arc;
+
+use ranim::{
+    color::palettes::manim,
+    components::{Anchor, ScaleHint},
+    glam::{DVec2, DVec3, dvec3},
+    items::{
+        ItemId,
+        vitem::{svg::SvgItem, typst::typst_svg},
+    },
+    prelude::*,
+};
+
+use crate::{
+    TimeSurface,
+    event::{Event, EventSink, OutOfBounds, OutOfBoundsPolicy, Polarity, SensorGeometry},
+    filter::EventFilter,
+    normalize::SharedReference,
+    schedule::FrameScheduler,
+};
+
+/// Several [`TimeSurface`]s side by side, each with its own filter and a title, fed with the
+/// same events and normalized against the same times so their colors are comparable.
+pub struct SurfaceComparison {
+    pub surfaces: Vec<ItemId<TimeSurface>>,
+    pub titles: Vec<ItemId<SvgItem>>,
+    /// The geometry shared by the surfaces
+    pub geometry: SensorGeometry,
+}
+
+/// The box of a surface in the frame, its title goes above it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Slot {
+    center: DVec3,
+    width: f64,
+    height: f64,
+    title_size: f64,
+}
+
+impl Slot {
+    /// `cnt` slots side by side in the `frame_width` x `frame_height` frame centered on
+    /// `center`, leaving a margin around them and room for the titles above them.
+    fn layout(center: DVec3, frame_width: f64, frame_height: f64, cnt: usize) -> Vec<Slot> {
+        let cnt_f = cnt.max(1) as f64;
+        let margin = frame_height * 0.05;
+        let gap = frame_width * 0.03;
+        let title_size = frame_height * 0.05;
+        let width = (frame_width - margin * 2.0 - gap * (cnt_f - 1.0)) / cnt_f;
+        let height = frame_height - margin * 2.0 - title_size * 2.0;
+        (0..cnt)
+            .map(|i| Slot {
+                center: center
+                    + dvec3(
+                        (i as f64 - (cnt_f - 1.0) / 2.0) * (width + gap),
+                        -title_size,
+                        0.0,
+                    ),
+                width,
+                height,
+                title_size,
+            })
+            .collect()
+    }
+    /// The box that the title of a surface whose top is at `surface_top` is fitted in.
+    fn title_box(&self, surface_top: f64) -> [DVec3; 2] {
+        let bottom = dvec3(self.center.x, surface_top + self.title_size * 0.5, 0.0);
+        [
+            bottom - DVec3::X * self.width / 2.0,
+            bottom + dvec3(self.width / 2.0, self.title_size, 0.0),
+        ]
+    }
+}
+
+/// Normalize every surface against the times of all of them.
+fn share_reference(surfaces: &mut [TimeSurface]) {
+    let mut reference = SharedReference::default();
+    for surface in surfaces.iter() {
+        for channel in [None, Some(Polarity::On), Some(Polarity::Off)] {
+            reference.merge(channel, &surface.channel_times(channel));
+        }
+    }
+    let reference = Arc::new(reference);
+    for surface in surfaces {
+        surface.set_shared_reference(Some(reference.clone()));
+    }
+}
+
+impl SurfaceComparison {
+    /// Insert a `width` x `height` surface for each `(title, filter)`, fitted side by side in
+    /// the frame of `camera`, whose size at a scale of 1.0 is `frame_size`, see
+    /// [`crate::FRAME_SIZE`].
+    ///
+    /// `configure` is applied to every surface before the layout, to set the mode, the
+    /// normalization and so on.
+    pub fn insert(
+        r: &mut RanimScene,
+        camera: &CameraFrame,
+        frame_size: DVec2,
+        width: usize,
+        height: usize,
+        filters: Vec<(&str, Arc<dyn EventFilter>)>,
+        configure: impl Fn(&mut TimeSurface),
+    ) -> Self {
+        let slots = Slot::layout(
+            dvec3(camera.pos.x, camera.pos.y, 0.0),
+            frame_size.x * camera.scale,
+            frame_size.y * camera.scale,
+            filters.len(),
+        );
+        let mut surfaces = filters
+            .iter()
+            .zip(&slots)
+            .map(|((_, filter), slot)| {
+                let mut surface = TimeSurface::new(width, height);
+                surface.set_filter(filter.clone());
+                configure(&mut surface);
+                surface.fit_in(slot.center, slot.width, slot.height);
+                surface
+            })
+            .collect::<Vec<_>>();
+        share_reference(&mut surfaces);
+
+        let (surfaces, titles) = surfaces
+            .into_iter()
+            .zip(filters.iter().zip(&slots))
+            .map(|(surface, ((title, _), slot))| {
+                // The panels set up by `configure` may be smaller than the grid.
+                let [_, _, surface_top] = surface.get_bounding_box();
+                let [box_min, box_max] = slot.title_box(surface_top.y);
+                let title = SvgItem::new(typst_svg(title)).with(|item| {
+                    item.scale_to(ScaleHint::PorportionalY(box_max.y - box_min.y));
+                    let [min, _, max] = item.get_bounding_box();
+                    let box_width = box_max.x - box_min.x;
+                    if max.x - min.x > box_width {
+                        item.scale_by_anchor(
+                            DVec3::splat(box_width / (max.x - min.x)),
+                            Anchor::ORIGIN,
+                        );
+                    }
+                    item.set_fill_color(manim::WHITE).put_anchor_on(
+                        Anchor::edge(0, -1, 0),
+                        dvec3((box_min.x + box_max.x) / 2.0, box_min.y, 0.0),
+                    );
+                });
+                (r.insert_and_show(surface), r.insert_and_show(title))
+            })
+            .unzip();
+        Self {
+            surfaces,
+            titles,
+            geometry: SensorGeometry::new(width, height),
+        }
+    }
+
+    /// Feed the events to every surface, with one update per frame.
+    ///
+    /// The events are checked against the geometry first, nothing is played if `policy`
+    /// stops at an event out of it.
+    pub fn play(
+        &self,
+        r: &mut RanimScene,
+        scheduler: &FrameScheduler,
+        events: impl IntoIterator<Item = Event>,
+        policy: OutOfBoundsPolicy,
+    ) -> Result<(), OutOfBounds> {
+        let events = policy.apply(self.geometry, events)?;
+        scheduler.play_frames(
+            r,
+            events,
+            |event| event.t,
+            |r, events| {
+                let mut states = self
+                    .surfaces
+                    .iter()
+                    .map(|surface| r.timeline(surface).snapshot())
+                    .collect::<Vec<_>>();
+                for state in states.iter_mut() {
+                    // Already checked against the geometry.
+                    state.feed(events, OutOfBoundsPolicy::Error).unwrap();
+                }
+                share_reference(&mut states);
+                for (surface, state) in self.surfaces.iter().zip(states) {
+                    r.timeline_mut(surface)
+                        .update_with(|surface| *surface = state);
+                }
+            },
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        FRAME_SIZE,
+        filter::{BackgroundActivityFilter, PassThrough, RefractoryFilter},
+    };
+
+    fn overlaps([a_min, a_max]: [DVec3; 2], [b_min, b_max]: [DVec3; 2]) -> bool {
+        a_min.x < b_max.x && b_min.x < a_max.x && a_min.y < b_max.y && b_min.y < a_max.y
+    }
+
+    #[test]
+    fn panels_and_titles_fit_in_the_frame() {
+        let center = dvec3(1.0, -2.0, 0.0);
+        let (frame_width, frame_height) = (FRAME_SIZE.x * 1.5, FRAME_SIZE.y * 1.5);
+        let frame_min = center - dvec3(frame_width, frame_height, 0.0) / 2.0;
+        let frame_max = center + dvec3(frame_width, frame_height, 0.0) / 2.0;
+        let inside = |[min, max]: [DVec3; 2]| {
+            min.x >= frame_min.x - 1e-9
+                && min.y >= frame_min.y - 1e-9
+                && max.x <= frame_max.x + 1e-9
+                && max.y <= frame_max.y + 1e-9
+        };
+        for cnt in 1..=4 {
+            for (width, height) in [(8, 8), (16, 4), (3, 12)] {
+                let boxes = Slot::layout(center, frame_width, frame_height, cnt)
+                    .into_iter()
+                    .flat_map(|slot| {
+                        let mut surface = TimeSurface::new(width, height);
+                        surface.fit_in(slot.center, slot.width, slot.height);
+                        let [min, _, max] = surface.get_bounding_box();
+                        [[min, max], slot.title_box(max.y)]
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(boxes.len(), cnt * 2);
+                for (i, a) in boxes.iter().enumerate() {
+                    assert!(inside(*a), "{cnt} slots, {a:?} is out of the frame");
+                    for b in &boxes[i + 1..] {
+                        assert!(!overlaps(*a, *b), "{cnt} slots, {a:?} overlaps {b:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn surfaces_share_the_normalization() {
+        let filters: Vec<Arc<dyn EventFilter>> = vec![
+            Arc::new(PassThrough),
+            Arc::new(BackgroundActivityFilter::new(10)),
+            Arc::new(RefractoryFilter::new(100)),
+        ];
+        let mut surfaces = filters
+            .into_iter()
+            .map(|filter| {
+                let mut surface = TimeSurface::new(4, 4);
+                surface.set_filter(filter);
+                surface
+            })
+            .collect::<Vec<_>>();
+        share_reference(&mut surfaces);
+
+        let events = [
+            Event::new(0, 0, 0, Polarity::On),
+            Event::new(5, 1, 0, Polarity::Off),
+            Event::new(50, 3, 3, Polarity::On),
+            Event::new(60, 1, 0, Polarity::On),
+        ];
+        for surface in surfaces.iter_mut() {
+            surface.feed(&events, OutOfBoundsPolicy::Error).unwrap();
+        }
+        // The filters keep different events
+        assert_ne!(
+            surfaces[0].channel_times(None),
+            surfaces[1].channel_times(None)
+        );
+        share_reference(&mut surfaces);
+
+        for channel in [None, Some(Polarity::On), Some(Polarity::Off)] {
+            let merged = surfaces
+                .iter()
+                .flat_map(|surface| surface.channel_times(channel))
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            for surface in &surfaces {
+                assert_eq!(surface.normalization_reference(channel), merged);
+            }
+        }
+    }
+}
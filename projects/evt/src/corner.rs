@@ -0,0 +1,222 @@
+//! Event-based corner detectors on the surface of active events.
+//!
+//! Both detectors look at the timestamps on two Bresenham circles around the event, of radius
+//! 3 (16 pixels) and 4 (20 pixels). Where an edge moves past, the newer timestamps form one
+//! arc of each circle; the event is a corner when that arc is short or long enough, unlike a
+//! straight edge, which splits the circles in halves. See Mueggler et al., "Fast Event-based
+//! Corner Detection", 2017 (eFAST), and Alzugaray and Chli, "Asynchronous Corner Detection and
+//! Tracking for Event Cameras in Real Time", 2018 (Arc*).
+
+use crate::filter::TimestampMap;
+
+/// The offsets of the circle of radius 3 around the event. y goes down, so they start below the
+/// event at (0, 3) and go counter-clockwise on screen.
+pub const INNER_CIRCLE: [(isize, isize); 16] = [
+    (0, 3),
+    (1, 3),
+    (2, 2),
+    (3, 1),
+    (3, 0),
+    (3, -1),
+    (2, -2),
+    (1, -3),
+    (0, -3),
+    (-1, -3),
+    (-2, -2),
+    (-3, -1),
+    (-3, 0),
+    (-3, 1),
+    (-2, 2),
+    (-1, 3),
+];
+
+/// The offsets of the circle of radius 4 around the event. y goes down, so they start below the
+/// event at (0, 4) and go counter-clockwise on screen.
+pub const OUTER_CIRCLE: [(isize, isize); 20] = [
+    (0, 4),
+    (1, 4),
+    (2, 3),
+    (3, 2),
+    (4, 1),
+    (4, 0),
+    (4, -1),
+    (3, -2),
+    (2, -3),
+    (1, -4),
+    (0, -4),
+    (-1, -4),
+    (-2, -3),
+    (-3, -2),
+    (-4, -1),
+    (-4, 0),
+    (-4, 1),
+    (-3, 2),
+    (-2, 3),
+    (-1, 4),
+];
+
+/// The arc lengths that make a corner on the inner and the outer circle.
+const INNER_ARC: std::ops::RangeInclusive<usize> = 3..=6;
+const OUTER_ARC: std::ops::RangeInclusive<usize> = 4..=8;
+
+pub trait CornerDetector: Send + Sync {
+    /// Whether the event at `(x, y)` is a corner.
+    ///
+    /// `map` is the state after the event arrives, usually with the events of its polarity only.
+    fn is_corner(&self, map: &dyn TimestampMap, x: usize, y: usize) -> bool;
+}
+
+/// The timestamps on `circle` around `(x, y)`, `None` if the circle is not within the sensor.
+pub fn circle_times(
+    map: &dyn TimestampMap,
+    x: usize,
+    y: usize,
+    circle: &[(isize, isize)],
+) -> Option<Vec<Option<usize>>> {
+    circle
+        .iter()
+        .map(|&(dx, dy)| {
+            let nx = x.checked_add_signed(dx).filter(|&nx| nx < map.width())?;
+            let ny = y.checked_add_signed(dy).filter(|&ny| ny < map.height())?;
+            Some(map.last_t(nx, ny))
+        })
+        .collect()
+}
+
+/// The lengths of the arcs of the newest timestamps on a circle, which are newer than all the
+/// other timestamps, a pixel without event being the oldest.
+///
+/// Such an arc of length `k` holds the `k` newest timestamps, so the arcs are found by growing
+/// one from the newest timestamp towards the newer of its two neighbours.
+pub fn newest_arcs(ts: &[Option<usize>]) -> Vec<usize> {
+    let n = ts.len();
+    let Some(newest) = (0..n).max_by_key(|&i| (ts[i], std::cmp::Reverse(i))) else {
+        return vec![];
+    };
+    // The arc is `start..start + len`, wrapping around.
+    let (mut start, mut len) = (newest, 1);
+    let mut oldest_in_arc = ts[newest];
+    let mut arcs = vec![];
+    while len < n {
+        let newest_out_of_arc = (len..n).map(|i| ts[(start + i) % n]).max().unwrap();
+        if oldest_in_arc > newest_out_of_arc {
+            arcs.push(len);
+        }
+        let (cw, ccw) = ((start + len) % n, (start + n - 1) % n);
+        if ts[cw] >= ts[ccw] {
+            oldest_in_arc = oldest_in_arc.min(ts[cw]);
+        } else {
+            oldest_in_arc = oldest_in_arc.min(ts[ccw]);
+            start = ccw;
+        }
+        len += 1;
+    }
+    arcs
+}
+
+/// eFAST: a corner if both circles have an arc of the newest timestamps that is 3 to 6 pixels
+/// long on the inner circle and 4 to 8 pixels long on the outer one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EFast;
+
+impl CornerDetector for EFast {
+    fn is_corner(&self, map: &dyn TimestampMap, x: usize, y: usize) -> bool {
+        [
+            (&INNER_CIRCLE[..], INNER_ARC),
+            (&OUTER_CIRCLE[..], OUTER_ARC),
+        ]
+        .into_iter()
+        .all(|(circle, range)| {
+            circle_times(map, x, y, circle)
+                .is_some_and(|ts| newest_arcs(&ts).into_iter().any(|len| range.contains(&len)))
+        })
+    }
+}
+
+/// Arc*: like [`EFast`], and the arc may also be the complement of the newest arc, i.e. the
+/// older timestamps, so that a corner is found from both its convex and its concave side.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArcStar;
+
+impl CornerDetector for ArcStar {
+    fn is_corner(&self, map: &dyn TimestampMap, x: usize, y: usize) -> bool {
+        [
+            (&INNER_CIRCLE[..], INNER_ARC),
+            (&OUTER_CIRCLE[..], OUTER_ARC),
+        ]
+        .into_iter()
+        .all(|(circle, range)| {
+            circle_times(map, x, y, circle).is_some_and(|ts| {
+                newest_arcs(&ts)
+                    .into_iter()
+                    .any(|len| range.contains(&len) || range.contains(&(circle.len() - len)))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        filter::tests::Timestamps,
+        generator::{Generator, MovingSquare},
+    };
+
+    #[test]
+    fn newest_arcs_on_hand_built_circles() {
+        assert_eq!(newest_arcs(&[]), Vec::<usize>::new());
+        assert_eq!(newest_arcs(&[None; 16]), Vec::<usize>::new());
+
+        // Four new timestamps wrapping around the start, each prefix by age is an arc.
+        let mut ts = [Some(10); 16];
+        for (i, t) in [(14, 101), (15, 103), (0, 104), (1, 102)] {
+            ts[i] = Some(t);
+        }
+        assert_eq!(newest_arcs(&ts), vec![1, 2, 3, 4]);
+
+        // A straight edge, half of the circle at once, against pixels without event.
+        let ts = std::array::from_fn::<_, 16, _>(|i| (4..12).contains(&i).then_some(50));
+        assert_eq!(newest_arcs(&ts), vec![8]);
+
+        // Two separate new arcs don't make one arc.
+        let ts = std::array::from_fn::<_, 16, _>(|i| Some(if i % 8 < 3 { 100 } else { 1 }));
+        assert_eq!(newest_arcs(&ts), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn corners_of_a_moving_square() {
+        let (width, height) = (32, 32);
+        let square = MovingSquare::new(10.0, (5.0, 2.5))
+            .with_start(6.0, 8.0)
+            .with_step(5);
+        let events = square.generate(0, width, height, 2000);
+        for detector in [&EFast as &dyn CornerDetector, &ArcStar] {
+            // The detectors see the events of the polarity of the event only.
+            let mut maps: [_; 2] = std::array::from_fn(|_| Timestamps::new(width, height));
+            let (mut near, mut near_corners) = (0, 0);
+            for labeled in &events {
+                let event = labeled.event;
+                let (x, y) = (event.x as usize, event.y as usize);
+                let map = &mut maps[event.polarity as usize];
+                map.set(x, y, Some(event.t as usize));
+                let is_corner = detector.is_corner(map, x, y);
+                let distance = square
+                    .corners(width, height, event.t)
+                    .into_iter()
+                    .map(|(cx, cy)| (x as f64 + 0.5 - cx).hypot(y as f64 + 0.5 - cy))
+                    .fold(f64::MAX, f64::min);
+                // The middle of the edges is 5 pixels away from the corners.
+                assert!(!is_corner || distance < 3.5, "{event:?} is a corner");
+                if distance < 2.5 {
+                    near += 1;
+                    near_corners += is_corner as usize;
+                }
+            }
+            assert!(
+                near_corners * 4 >= near,
+                "{near_corners} of {near} near corners"
+            );
+        }
+    }
+}
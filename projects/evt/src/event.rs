@@ -0,0 +1,207 @@
+use std::fmt;
+
+/// The polarity of an event, i.e. whether the brightness went up or down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Polarity {
+    Off,
+    On,
+}
+
+impl From<bool> for Polarity {
+    fn from(on: bool) -> Self {
+        if on { Polarity::On } else { Polarity::Off }
+    }
+}
+
+/// A single event, `t` is in microseconds.
+///
+/// Events are ordered by `t` first, so a sorted `Vec<Event>` is a valid stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Event {
+    pub t: u64,
+    pub x: u16,
+    pub y: u16,
+    pub polarity: Polarity,
+}
+
+impl Event {
+    pub fn new(t: u64, x: u16, y: u16, polarity: Polarity) -> Self {
+        Self { t, x, y, polarity }
+    }
+}
+
+/// The size of a sensor in pixels, the pixel `(x, y)` is at column `x` and row `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SensorGeometry {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl SensorGeometry {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height }
+    }
+    pub fn pixel_cnt(&self) -> usize {
+        self.width * self.height
+    }
+    pub fn contains(&self, event: &Event) -> bool {
+        self.index_of(event.x as usize, event.y as usize).is_some()
+    }
+    /// The row-major index of the pixel `(x, y)`, `None` if it is not on the sensor.
+    pub fn index_of(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+    /// The row-major index of the pixel of the event.
+    pub fn index(&self, event: &Event) -> Result<usize, OutOfBounds> {
+        self.index_of(event.x as usize, event.y as usize)
+            .ok_or(OutOfBounds {
+                event: *event,
+                geometry: *self,
+            })
+    }
+}
+
+/// An event whose pixel is not on the sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBounds {
+    pub event: Event,
+    pub geometry: SensorGeometry,
+}
+
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "event at ({}, {}), t = {}, is out of the {}x{} sensor",
+            self.event.x, self.event.y, self.event.t, self.geometry.width, self.geometry.height
+        )
+    }
+}
+
+impl std::error::Error for OutOfBounds {}
+
+/// What [`EventSink::feed`] does with an event out of the sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfBoundsPolicy {
+    /// Stop at the event and return the error
+    #[default]
+    Error,
+    /// Skip the event
+    Drop,
+}
+
+impl OutOfBoundsPolicy {
+    /// Check a stream against `geometry` before feeding it: keep the events on the sensor, and
+    /// drop the others or stop at the first one.
+    pub fn apply(
+        self,
+        geometry: SensorGeometry,
+        events: impl IntoIterator<Item = Event>,
+    ) -> Result<Vec<Event>, OutOfBounds> {
+        let mut kept = vec![];
+        for event in events {
+            match (geometry.index(&event), self) {
+                (Ok(_), _) => kept.push(event),
+                (Err(_), OutOfBoundsPolicy::Drop) => {}
+                (Err(err), OutOfBoundsPolicy::Error) => return Err(err),
+            }
+        }
+        Ok(kept)
+    }
+}
+
+/// An item that events are fed to one by one, like the [`crate::TimeSurface`].
+pub trait EventSink {
+    fn geometry(&self) -> SensorGeometry;
+    /// Feed an event, an event out of the [`EventSink::geometry`] is an error and changes nothing.
+    fn accept(&mut self, event: &Event) -> Result<(), OutOfBounds>;
+    /// Feed the events in order, the events out of the geometry are handled by `policy`.
+    ///
+    /// Returns the count of the dropped events.
+    fn feed<'a>(
+        &mut self,
+        events: impl IntoIterator<Item = &'a Event>,
+        policy: OutOfBoundsPolicy,
+    ) -> Result<usize, OutOfBounds>
+    where
+        Self: Sized,
+    {
+        let mut dropped = 0;
+        for event in events {
+            match (self.accept(event), policy) {
+                (Ok(()), _) => {}
+                (Err(_), OutOfBoundsPolicy::Drop) => dropped += 1,
+                (Err(err), OutOfBoundsPolicy::Error) => return Err(err),
+            }
+        }
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn geometry() -> impl Strategy<Value = SensorGeometry> {
+        (0..64usize, 0..64usize).prop_map(|(width, height)| SensorGeometry::new(width, height))
+    }
+
+    fn event() -> impl Strategy<Value = Event> {
+        (any::<u64>(), 0..80u16, 0..80u16, any::<bool>())
+            .prop_map(|(t, x, y, on)| Event::new(t, x, y, Polarity::from(on)))
+    }
+
+    proptest! {
+        #[test]
+        fn index_of_is_row_major(geometry in geometry(), x in 0..80usize, y in 0..80usize) {
+            match geometry.index_of(x, y) {
+                Some(idx) => {
+                    prop_assert!(x < geometry.width && y < geometry.height);
+                    prop_assert!(idx < geometry.pixel_cnt());
+                    prop_assert_eq!((idx % geometry.width, idx / geometry.width), (x, y));
+                }
+                None => prop_assert!(x >= geometry.width || y >= geometry.height),
+            }
+        }
+
+        #[test]
+        fn index_agrees_with_index_of(geometry in geometry(), event in event()) {
+            match geometry.index(&event) {
+                Ok(idx) => {
+                    prop_assert_eq!(geometry.index_of(event.x as usize, event.y as usize), Some(idx));
+                }
+                Err(err) => {
+                    prop_assert!(!geometry.contains(&event));
+                    prop_assert_eq!(err, OutOfBounds { event, geometry });
+                }
+            }
+        }
+
+        #[test]
+        fn drop_keeps_the_events_on_the_sensor(
+            geometry in geometry(),
+            events in prop::collection::vec(event(), 0..32),
+        ) {
+            let kept = OutOfBoundsPolicy::Drop.apply(geometry, events.clone()).unwrap();
+            let expected = events
+                .into_iter()
+                .filter(|event| geometry.contains(event))
+                .collect::<Vec<_>>();
+            prop_assert_eq!(kept, expected);
+        }
+
+        #[test]
+        fn error_stops_at_the_first_event_off_the_sensor(
+            geometry in geometry(),
+            events in prop::collection::vec(event(), 0..32),
+        ) {
+            let result = OutOfBoundsPolicy::Error.apply(geometry, events.clone());
+            match events.iter().find(|event| !geometry.contains(event)) {
+                Some(&event) => prop_assert_eq!(result, Err(OutOfBounds { event, geometry })),
+                None => prop_assert_eq!(result, Ok(events)),
+            }
+        }
+    }
+}
@@ -0,0 +1,400 @@
+//! Event denoising filters.
+//!
+//! A filter decides whether an event is signal or noise by looking at the timestamps of the
+//! events that arrived before it, it doesn't hold any state itself so the same filter can be
+//! shared between the snapshots of a [`crate::TimeSurface`].
+
+use std::sync::Arc;
+
+/// Read access to the last event timestamp of each pixel.
+pub trait TimestampMap {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    /// The timestamp of the last event at `(x, y)`, `None` if there is none yet or if `(x, y)`
+    /// is not on the map.
+    fn last_t(&self, x: usize, y: usize) -> Option<usize>;
+}
+
+/// The pixels in the `(2 * radius + 1)^2` window around `(x, y)`, without `(x, y)` itself.
+pub fn neighbours(
+    map: &dyn TimestampMap,
+    x: usize,
+    y: usize,
+    radius: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let (width, height) = (map.width(), map.height());
+    let xs = x.saturating_sub(radius)..(x + radius + 1).min(width);
+    let ys = y.saturating_sub(radius)..(y + radius + 1).min(height);
+    ys.flat_map(move |ny| xs.clone().map(move |nx| (nx, ny)))
+        .filter(move |&(nx, ny)| (nx, ny) != (x, y))
+}
+
+pub trait EventFilter: Send + Sync {
+    /// Whether the event at `(x, y)` and time `t` is signal.
+    ///
+    /// `map` is the state before the event arrives.
+    fn accept(&self, map: &dyn TimestampMap, t: usize, x: usize, y: usize) -> bool;
+    /// The neighbours that count as support for the event, only used for visualization.
+    fn supporters(
+        &self,
+        _map: &dyn TimestampMap,
+        _t: usize,
+        _x: usize,
+        _y: usize,
+    ) -> Vec<(usize, usize)> {
+        vec![]
+    }
+    /// Both [`EventFilter::accept`] and [`EventFilter::supporters`], filters that find the
+    /// supporters on the way to the decision override it to scan the neighbourhood once.
+    fn decide(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> (bool, Vec<(usize, usize)>) {
+        (self.accept(map, t, x, y), self.supporters(map, t, x, y))
+    }
+    /// A short description of the decision rule, like `dt < 30`, shown next to the decision.
+    fn describe(&self) -> Option<String> {
+        None
+    }
+}
+
+/// The neighbours in the `radius` window that fired within `dt` before `t`.
+fn recent_neighbours(
+    map: &dyn TimestampMap,
+    t: usize,
+    x: usize,
+    y: usize,
+    radius: usize,
+    dt: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    neighbours(map, x, y, radius)
+        .filter(move |&(nx, ny)| map.last_t(nx, ny).is_some_and(|nt| nt.abs_diff(t) < dt))
+}
+
+/// Accepts every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PassThrough;
+
+impl EventFilter for PassThrough {
+    fn accept(&self, _map: &dyn TimestampMap, _t: usize, _x: usize, _y: usize) -> bool {
+        true
+    }
+    fn describe(&self) -> Option<String> {
+        Some("all".to_string())
+    }
+}
+
+/// Background activity filter.
+///
+/// An event is signal if any of its 8 neighbours fired within `dt` before it.
+#[derive(Debug, Clone, Copy)]
+pub struct BackgroundActivityFilter {
+    pub dt: usize,
+}
+
+impl BackgroundActivityFilter {
+    pub fn new(dt: usize) -> Self {
+        Self { dt }
+    }
+}
+
+impl EventFilter for BackgroundActivityFilter {
+    fn accept(&self, map: &dyn TimestampMap, t: usize, x: usize, y: usize) -> bool {
+        recent_neighbours(map, t, x, y, 1, self.dt).next().is_some()
+    }
+    fn supporters(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> Vec<(usize, usize)> {
+        recent_neighbours(map, t, x, y, 1, self.dt).collect()
+    }
+    fn decide(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> (bool, Vec<(usize, usize)>) {
+        let supporters = self.supporters(map, t, x, y);
+        (!supporters.is_empty(), supporters)
+    }
+    fn describe(&self) -> Option<String> {
+        Some(format!("dt < {}", self.dt))
+    }
+}
+
+/// Refractory period filter.
+///
+/// Drops the events of a pixel that fired again within `period`, which removes the bursts of
+/// a hot pixel.
+#[derive(Debug, Clone, Copy)]
+pub struct RefractoryFilter {
+    pub period: usize,
+}
+
+impl RefractoryFilter {
+    pub fn new(period: usize) -> Self {
+        Self { period }
+    }
+}
+
+impl EventFilter for RefractoryFilter {
+    fn accept(&self, map: &dyn TimestampMap, t: usize, x: usize, y: usize) -> bool {
+        map.last_t(x, y)
+            .is_none_or(|last_t| last_t.abs_diff(t) >= self.period)
+    }
+    fn describe(&self) -> Option<String> {
+        Some(format!("own dt >= {}", self.period))
+    }
+}
+
+/// K-nearest-neighbour support filter.
+///
+/// An event is signal if at least `k` pixels in the `(2 * radius + 1)^2` window around it
+/// fired within `dt` before it.
+#[derive(Debug, Clone, Copy)]
+pub struct KnnFilter {
+    pub k: usize,
+    pub dt: usize,
+    pub radius: usize,
+}
+
+impl KnnFilter {
+    pub fn new(k: usize, dt: usize) -> Self {
+        Self { k, dt, radius: 1 }
+    }
+    pub fn with_radius(mut self, radius: usize) -> Self {
+        self.radius = radius;
+        self
+    }
+}
+
+impl EventFilter for KnnFilter {
+    fn accept(&self, map: &dyn TimestampMap, t: usize, x: usize, y: usize) -> bool {
+        recent_neighbours(map, t, x, y, self.radius, self.dt)
+            .take(self.k)
+            .count()
+            >= self.k
+    }
+    fn supporters(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> Vec<(usize, usize)> {
+        recent_neighbours(map, t, x, y, self.radius, self.dt).collect()
+    }
+    fn decide(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> (bool, Vec<(usize, usize)>) {
+        let supporters = self.supporters(map, t, x, y);
+        (supporters.len() >= self.k, supporters)
+    }
+    fn describe(&self) -> Option<String> {
+        Some(format!("{} with dt < {}", self.k, self.dt))
+    }
+}
+
+/// Chaining two filters, the event is signal only if both of them accept it.
+impl<A: EventFilter, B: EventFilter> EventFilter for (A, B) {
+    fn accept(&self, map: &dyn TimestampMap, t: usize, x: usize, y: usize) -> bool {
+        self.0.accept(map, t, x, y) && self.1.accept(map, t, x, y)
+    }
+    fn supporters(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> Vec<(usize, usize)> {
+        let mut supporters = self.0.supporters(map, t, x, y);
+        supporters.extend(self.1.supporters(map, t, x, y));
+        supporters.sort_unstable();
+        supporters.dedup();
+        supporters
+    }
+    fn decide(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> (bool, Vec<(usize, usize)>) {
+        let (accepted, mut supporters) = self.0.decide(map, t, x, y);
+        let (accepted_1, supporters_1) = self.1.decide(map, t, x, y);
+        supporters.extend(supporters_1);
+        supporters.sort_unstable();
+        supporters.dedup();
+        (accepted && accepted_1, supporters)
+    }
+    fn describe(&self) -> Option<String> {
+        match (self.0.describe(), self.1.describe()) {
+            (Some(a), Some(b)) => Some(format!("{a}, {b}")),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// A shared filter, so that several surfaces can use the same one.
+impl<F: EventFilter + ?Sized> EventFilter for Arc<F> {
+    fn accept(&self, map: &dyn TimestampMap, t: usize, x: usize, y: usize) -> bool {
+        (**self).accept(map, t, x, y)
+    }
+    fn supporters(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> Vec<(usize, usize)> {
+        (**self).supporters(map, t, x, y)
+    }
+    fn decide(
+        &self,
+        map: &dyn TimestampMap,
+        t: usize,
+        x: usize,
+        y: usize,
+    ) -> (bool, Vec<(usize, usize)>) {
+        (**self).decide(map, t, x, y)
+    }
+    fn describe(&self) -> Option<String> {
+        (**self).describe()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A map whose timestamps are set by hand.
+    pub(crate) struct Timestamps {
+        width: usize,
+        height: usize,
+        ts: Vec<Option<usize>>,
+    }
+
+    impl Timestamps {
+        pub(crate) fn new(width: usize, height: usize) -> Self {
+            Self::from_fn(width, height, |_, _| None)
+        }
+        pub(crate) fn from_fn(
+            width: usize,
+            height: usize,
+            f: impl Fn(usize, usize) -> Option<usize>,
+        ) -> Self {
+            let ts = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| f(x, y))
+                .collect();
+            Self { width, height, ts }
+        }
+        pub(crate) fn set(&mut self, x: usize, y: usize, t: Option<usize>) {
+            self.ts[y * self.width + x] = t;
+        }
+    }
+
+    impl TimestampMap for Timestamps {
+        fn width(&self) -> usize {
+            self.width
+        }
+        fn height(&self) -> usize {
+            self.height
+        }
+        fn last_t(&self, x: usize, y: usize) -> Option<usize> {
+            (x < self.width && y < self.height)
+                .then(|| self.ts[y * self.width + x])
+                .flatten()
+        }
+    }
+
+    #[test]
+    fn decide_is_accept_and_supporters() {
+        let map = Timestamps::from_fn(5, 5, |x, y| ((x + y) % 3 != 0).then_some(x * 7 + y * 3));
+        let filters: Vec<Arc<dyn EventFilter>> = vec![
+            Arc::new(PassThrough),
+            Arc::new(BackgroundActivityFilter::new(10)),
+            Arc::new(RefractoryFilter::new(5)),
+            Arc::new(KnnFilter::new(2, 10)),
+            Arc::new(KnnFilter::new(4, 20).with_radius(2)),
+            Arc::new((BackgroundActivityFilter::new(10), KnnFilter::new(3, 15))),
+        ];
+        for filter in filters {
+            for (t, x, y) in (20..40)
+                .step_by(3)
+                .flat_map(|t| (0..5).flat_map(move |y| (0..5).map(move |x| (t, x, y))))
+            {
+                assert_eq!(
+                    filter.decide(&map, t, x, y),
+                    (
+                        filter.accept(&map, t, x, y),
+                        filter.supporters(&map, t, x, y)
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn background_activity_supporters() {
+        let mut map = Timestamps::new(3, 3);
+        map.set(0, 0, Some(95));
+        map.set(2, 1, Some(80));
+        map.set(1, 2, Some(100));
+        let filter = BackgroundActivityFilter::new(10);
+        assert_eq!(filter.decide(&map, 100, 1, 1), (true, vec![(0, 0), (1, 2)]));
+        assert_eq!(filter.decide(&map, 120, 1, 1), (false, vec![]));
+    }
+
+    #[test]
+    fn refractory_is_per_pixel() {
+        let mut map = Timestamps::new(3, 3);
+        map.set(1, 1, Some(100));
+        map.set(0, 0, Some(98));
+        let filter = RefractoryFilter::new(10);
+        // Inside the period of its own pixel
+        assert!(!filter.accept(&map, 100, 1, 1));
+        assert!(!filter.accept(&map, 109, 1, 1));
+        // After the period
+        assert!(filter.accept(&map, 110, 1, 1));
+        assert!(filter.accept(&map, 200, 1, 1));
+        // The other pixels are not held by the events of (1, 1)
+        assert!(filter.accept(&map, 105, 2, 2));
+        assert!(filter.accept(&map, 105, 2, 1));
+        assert!(!filter.accept(&map, 105, 0, 0));
+    }
+
+    #[test]
+    fn knn_needs_k_supporters() {
+        let mut map = Timestamps::new(3, 3);
+        map.set(0, 0, Some(95));
+        map.set(1, 0, Some(98));
+        map.set(2, 2, Some(92));
+        // Too old to support
+        map.set(0, 2, Some(50));
+        for k in 1..=3 {
+            assert!(KnnFilter::new(k, 10).accept(&map, 100, 1, 1), "k = {k}");
+        }
+        assert!(!KnnFilter::new(4, 10).accept(&map, 100, 1, 1));
+        assert_eq!(
+            KnnFilter::new(4, 10).decide(&map, 100, 1, 1),
+            (false, vec![(0, 0), (1, 0), (2, 2)])
+        );
+
+        // One supporter fewer once the oldest one is out of `dt`
+        assert!(KnnFilter::new(3, 10).accept(&map, 101, 1, 1));
+        assert!(!KnnFilter::new(3, 10).accept(&map, 102, 1, 1));
+        assert!(KnnFilter::new(2, 10).accept(&map, 102, 1, 1));
+    }
+}
@@ -0,0 +1,230 @@
+//! Optical flow by fitting a local plane to the time surface.
+//!
+//! Around a moving edge the last event times form a surface `t(x, y)` that rises in the
+//! direction of the motion, and its gradient is the inverse of the velocity: an edge moving at
+//! `v` pixels per time unit along `x` gives `dt/dx = 1 / v`. The flow of an event is estimated
+//! by fitting a plane to the recent timestamps of its neighbourhood, see Benosman et al.,
+//! "Event-Based Visual Flow", 2014.
+
+use crate::filter::{TimestampMap, neighbours};
+
+/// A velocity in pixels per time unit, `vy` goes down like the sensor rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flow {
+    pub vx: f64,
+    pub vy: f64,
+}
+
+impl Flow {
+    /// The flow of the plane `t = a * x + b * y + c`, `None` if the plane is flat.
+    ///
+    /// The velocity is along the gradient `(a, b)` with the inverse of its length as speed.
+    pub fn from_plane(plane: &Plane) -> Option<Self> {
+        let norm = plane.a * plane.a + plane.b * plane.b;
+        if !norm.is_normal() {
+            return None;
+        }
+        Some(Self {
+            vx: plane.a / norm,
+            vy: plane.b / norm,
+        })
+    }
+    /// In pixels per time unit
+    pub fn speed(&self) -> f64 {
+        self.vx.hypot(self.vy)
+    }
+}
+
+/// The plane `t = a * x + b * y + c`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl Plane {
+    pub fn t_at(&self, x: f64, y: f64) -> f64 {
+        self.a * x + self.b * y + self.c
+    }
+    /// The least squares fit of the `(x, y, t)` points, `None` if there are fewer than three
+    /// points or they are all on a line, in which case the plane is not determined.
+    pub fn fit(points: &[(f64, f64, f64)]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+        let n = points.len() as f64;
+        let (sx, sy, st) = points
+            .iter()
+            .fold((0.0, 0.0, 0.0), |(sx, sy, st), &(x, y, t)| {
+                (sx + x, sy + y, st + t)
+            });
+        let (mx, my, mt) = (sx / n, sy / n, st / n);
+        // The normal equations of the centered points, which leaves the 2x2 system of the slopes.
+        let (mut sxx, mut sxy, mut syy, mut sxt, mut syt) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for &(x, y, t) in points {
+            let (x, y, t) = (x - mx, y - my, t - mt);
+            sxx += x * x;
+            sxy += x * y;
+            syy += y * y;
+            sxt += x * t;
+            syt += y * t;
+        }
+        let det = sxx * syy - sxy * sxy;
+        // Relative to the spread, the points of a line leave only rounding error in `det`.
+        if det <= 1e-9 * (sxx + syy) * (sxx + syy) {
+            return None;
+        }
+        let a = (sxt * syy - syt * sxy) / det;
+        let b = (syt * sxx - sxt * sxy) / det;
+        Some(Self {
+            a,
+            b,
+            c: mt - a * mx - b * my,
+        })
+    }
+}
+
+/// Estimates the flow of an event from the `(2 * radius + 1)^2` window around it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaneFit {
+    pub radius: usize,
+    /// Only the timestamps at most this much older or newer than the event are fitted
+    pub max_age: usize,
+    /// The least count of fitted points, including the event itself
+    pub min_points: usize,
+    /// After a first fit, the points further than this from the plane in time are dropped
+    /// and the rest is fitted again, `None` keeps all the points
+    pub max_residual: Option<f64>,
+}
+
+impl PlaneFit {
+    pub fn new(radius: usize, max_age: usize) -> Self {
+        Self {
+            radius,
+            max_age,
+            min_points: 5,
+            max_residual: None,
+        }
+    }
+    pub fn with_min_points(mut self, min_points: usize) -> Self {
+        self.min_points = min_points;
+        self
+    }
+    pub fn with_max_residual(mut self, max_residual: f64) -> Self {
+        self.max_residual = Some(max_residual);
+        self
+    }
+    /// The flow at `(x, y)` from the timestamps of `map`, which should already have the event.
+    ///
+    /// `None` if `(x, y)` has no event, if there are too few recent points or if they don't
+    /// determine a sloped plane.
+    pub fn estimate(&self, map: &dyn TimestampMap, x: usize, y: usize) -> Option<Flow> {
+        let t = map.last_t(x, y)?;
+        // Relative to the event, so that large timestamps don't lose precision.
+        let mut points = std::iter::once((x, y))
+            .chain(neighbours(map, x, y, self.radius))
+            .filter_map(|(nx, ny)| {
+                let nt = map
+                    .last_t(nx, ny)
+                    .filter(|&nt| nt.abs_diff(t) <= self.max_age)?;
+                Some((
+                    nx as f64 - x as f64,
+                    ny as f64 - y as f64,
+                    nt as f64 - t as f64,
+                ))
+            })
+            .collect::<Vec<_>>();
+        let min_points = self.min_points.max(3);
+        if points.len() < min_points {
+            return None;
+        }
+        let mut plane = Plane::fit(&points)?;
+        if let Some(max_residual) = self.max_residual {
+            let cnt = points.len();
+            points.retain(|&(x, y, t)| (t - plane.t_at(x, y)).abs() <= max_residual);
+            if points.len() < cnt {
+                if points.len() < min_points {
+                    return None;
+                }
+                plane = Plane::fit(&points)?;
+            }
+        }
+        Flow::from_plane(&plane)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::tests::Timestamps;
+
+    /// Every pixel of a 7x7 map on the plane `t = 1000 + a * x + b * y`.
+    fn plane(a: usize, b: usize) -> Timestamps {
+        Timestamps::from_fn(7, 7, |x, y| Some(1000 + a * x + b * y))
+    }
+
+    fn assert_flow(flow: Option<Flow>, vx: f64, vy: f64) {
+        let flow = flow.expect("a flow");
+        assert!(
+            (flow.vx - vx).abs() < 1e-9 && (flow.vy - vy).abs() < 1e-9,
+            "{flow:?} != ({vx}, {vy})"
+        );
+    }
+
+    #[test]
+    fn fits_a_plane_exactly() {
+        let points = [(0.0, 0.0), (1.0, 0.0), (0.0, 2.0), (3.0, 1.0)]
+            .map(|(x, y)| (x, y, 2.0 * x - 3.0 * y + 7.0));
+        let plane = Plane::fit(&points).unwrap();
+        assert!((plane.a - 2.0).abs() < 1e-9);
+        assert!((plane.b + 3.0).abs() < 1e-9);
+        assert!((plane.c - 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn known_plane_gives_its_velocity() {
+        // An edge moving along x at 0.1 pixels per time unit.
+        let map = plane(10, 0);
+        assert_flow(PlaneFit::new(1, 100).estimate(&map, 3, 3), 0.1, 0.0);
+        // The gradient (10, 5) gives the velocity (10, 5) / 125.
+        let map = plane(10, 5);
+        assert_flow(PlaneFit::new(2, 100).estimate(&map, 3, 3), 0.08, 0.04);
+        // At the border the window is cut, but the plane is the same.
+        assert_flow(PlaneFit::new(2, 100).estimate(&map, 0, 6), 0.08, 0.04);
+    }
+
+    #[test]
+    fn degenerate_points_have_no_plane() {
+        assert_eq!(Plane::fit(&[]), None);
+        assert_eq!(Plane::fit(&[(0.0, 0.0, 1.0), (1.0, 0.0, 2.0)]), None);
+        let collinear = [0.0, 1.0, 2.0, 3.0].map(|s| (s, 2.0 * s, s * 5.0));
+        assert_eq!(Plane::fit(&collinear), None);
+        let flat = Plane::fit(&[(0.0, 0.0, 4.0), (1.0, 0.0, 4.0), (0.0, 1.0, 4.0)]).unwrap();
+        assert_eq!(Flow::from_plane(&flat), None);
+    }
+
+    #[test]
+    fn too_few_points_have_no_flow() {
+        let mut map = plane(10, 0);
+        map.set(3, 3, None);
+        assert_eq!(PlaneFit::new(1, 100).estimate(&map, 3, 3), None);
+
+        // Only the row of the event is recent, which is a line.
+        let map = plane(1, 100);
+        let fit = PlaneFit::new(1, 10).with_min_points(3);
+        assert_eq!(fit.estimate(&map, 3, 3), None);
+        // The event and its two row neighbours are below the default of five points.
+        assert_eq!(PlaneFit::new(1, 10).estimate(&map, 3, 3), None);
+    }
+
+    #[test]
+    fn max_residual_drops_outliers() {
+        let mut map = plane(10, 5);
+        map.set(2, 4, Some(1000 + 20 + 20 + 60));
+        let fit = PlaneFit::new(2, 200);
+        let flow = fit.estimate(&map, 3, 3).unwrap();
+        assert!((flow.vx - 0.08).abs() > 1e-3 || (flow.vy - 0.04).abs() > 1e-3);
+        assert_flow(fit.with_max_residual(20.0).estimate(&map, 3, 3), 0.08, 0.04);
+    }
+}
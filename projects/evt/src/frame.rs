@@ -0,0 +1,778 @@
+//! Accumulated event representations.
+//!
+//! Unlike the [`crate::TimeSurface`], which keeps only the last event of each pixel, these
+//! items aggregate every event fed to them: the event-count image, the polarity-sum frame and
+//! the voxel grid. They take the events through [`EventSink`] like the surface does, so the
+//! same stream can be played on all of them.
+//!
+//! [`FrameView`] shows an ordinary grayscale frame on the same kind of grid, to put the input
+//! of the [`crate::simulator`] next to its events.
+
+use ranim::{
+    color::palettes::manim,
+    components::Anchor,
+    glam::{DVec3, dvec3},
+    items::vitem::{
+        VItem,
+        geometry::{Polygon, Rectangle},
+    },
+    prelude::*,
+    render::primitives::{Extract, vitem::VItemPrimitive},
+};
+
+use crate::{
+    FRAME_SIZE,
+    colormap::Colormap,
+    event::{Event, EventSink, OutOfBounds, Polarity, SensorGeometry},
+    glyph::glyph_label,
+    simulator::GrayFrame,
+};
+
+/// The placement of a `width` x `height` image of square pixels, row 0 is at the top.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelGrid {
+    pub width: usize,
+    pub height: usize,
+    pub center: DVec3,
+    pub pixel_size: f64,
+}
+
+impl PixelGrid {
+    /// A grid centered at the origin, which fills the height of the default frame.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            center: DVec3::ZERO,
+            pixel_size: FRAME_SIZE.y / height.max(1) as f64,
+        }
+    }
+    /// Fit the grid into the `width` x `height` box centered on `center`, the pixels stay square.
+    pub fn fit_in(&mut self, center: DVec3, width: f64, height: f64) -> &mut Self {
+        self.center = center;
+        self.pixel_size =
+            (width / self.width.max(1) as f64).min(height / self.height.max(1) as f64);
+        self
+    }
+    pub fn pixel_center(&self, x: usize, y: usize) -> DVec3 {
+        self.center
+            + dvec3(
+                x as f64 - (self.width as f64 - 1.0) / 2.0,
+                (self.height as f64 - 1.0) / 2.0 - y as f64,
+                0.0,
+            ) * self.pixel_size
+    }
+    pub fn geometry(&self) -> SensorGeometry {
+        SensorGeometry::new(self.width, self.height)
+    }
+    /// The whole image filled with `color`.
+    fn background(&self, color: color::AlphaColor<color::Srgb>) -> VItem {
+        VItem::from(
+            Rectangle::new(
+                self.width as f64 * self.pixel_size,
+                self.height as f64 * self.pixel_size,
+            )
+            .with(|rect| {
+                rect.set_fill_color(color)
+                    .set_stroke_color(manim::WHITE)
+                    .put_center_on(self.center);
+            }),
+        )
+    }
+    fn pixel(&self, x: usize, y: usize, color: color::AlphaColor<color::Srgb>) -> VItem {
+        // Slightly overlapping to avoid seams between pixels
+        let size = self.pixel_size * 1.02;
+        VItem::from(Rectangle::new(size, size).with(|rect| {
+            rect.set_fill_color(color)
+                .set_stroke_color(color.with_alpha(0.0))
+                .put_center_on(self.pixel_center(x, y));
+        }))
+    }
+    fn label(&self, text: &str, x: usize, y: usize) -> Vec<VItem> {
+        glyph_label(text, self.pixel_size * 0.3, self.pixel_center(x, y))
+            .with(|label| {
+                label.set_fill_color(manim::WHITE);
+            })
+            .0
+    }
+}
+
+impl BoundingBox for PixelGrid {
+    fn get_bounding_box(&self) -> [DVec3; 3] {
+        let half = dvec3(self.width as f64, self.height as f64, 0.0) * self.pixel_size / 2.0;
+        [self.center - half, self.center, self.center + half]
+    }
+}
+
+impl Shift for PixelGrid {
+    fn shift(&mut self, shift: DVec3) -> &mut Self {
+        self.center += shift;
+        self
+    }
+}
+
+/// Scale `origin`, a point of `item`, by `scale` about `anchor` and return it with the factor
+/// of the sizes.
+///
+/// The grids keep their square pixels, a non-uniform scale takes the smaller factor of x and y.
+pub fn square_scale(
+    item: &impl BoundingBox,
+    origin: DVec3,
+    scale: DVec3,
+    anchor: Anchor,
+) -> (DVec3, f64) {
+    let point = match anchor {
+        Anchor::Point(point) => point,
+        Anchor::Edge(edge) => item.get_bounding_box_point(edge),
+    };
+    let factor = scale.x.min(scale.y);
+    (point + (origin - point) * factor, factor)
+}
+
+impl Scale for PixelGrid {
+    /// See [`square_scale`].
+    fn scale_by_anchor(&mut self, scale: DVec3, anchor: Anchor) -> &mut Self {
+        let (center, factor) = square_scale(self, self.center, scale, anchor);
+        self.center = center;
+        self.pixel_size *= factor;
+        self
+    }
+}
+
+macro_rules! impl_grid_traits {
+    ($item:ty) => {
+        impl BoundingBox for $item {
+            fn get_bounding_box(&self) -> [DVec3; 3] {
+                self.grid.get_bounding_box()
+            }
+        }
+
+        impl Shift for $item {
+            fn shift(&mut self, shift: DVec3) -> &mut Self {
+                self.grid.shift(shift);
+                self
+            }
+        }
+
+        impl Scale for $item {
+            fn scale_by_anchor(&mut self, scale: DVec3, anchor: Anchor) -> &mut Self {
+                self.grid.scale_by_anchor(scale, anchor);
+                self
+            }
+        }
+    };
+}
+pub(crate) use impl_grid_traits;
+
+// MARK: FrameView
+
+/// A grayscale frame drawn as a grid of pixels.
+#[derive(Clone)]
+pub struct FrameView {
+    grid: PixelGrid,
+    frame: GrayFrame,
+}
+
+impl FrameView {
+    pub fn new(frame: GrayFrame) -> Self {
+        Self {
+            grid: PixelGrid::new(frame.width, frame.height),
+            frame,
+        }
+    }
+    pub fn fit_in(&mut self, center: DVec3, width: f64, height: f64) -> &mut Self {
+        self.grid.fit_in(center, width, height);
+        self
+    }
+    /// Show another frame, a frame of another size is fitted into the box of the current one.
+    pub fn set_frame(&mut self, frame: GrayFrame) -> &mut Self {
+        if (frame.width, frame.height) != (self.grid.width, self.grid.height) {
+            let [min, center, max] = self.grid.get_bounding_box();
+            self.grid = PixelGrid::new(frame.width, frame.height);
+            self.grid.fit_in(center, max.x - min.x, max.y - min.y);
+        }
+        self.frame = frame;
+        self
+    }
+    pub fn frame(&self) -> &GrayFrame {
+        &self.frame
+    }
+}
+
+impl_grid_traits!(FrameView);
+
+impl Extract for FrameView {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let mut items = vec![self.grid.background(color::AlphaColor::BLACK)];
+        for y in 0..self.frame.height {
+            for x in 0..self.frame.width {
+                let value = self.frame.intensity(x, y).clamp(0.0, 1.0);
+                if value > 0.0 {
+                    let color = color::AlphaColor::new([value, value, value, 1.0]);
+                    items.push(self.grid.pixel(x, y, color));
+                }
+            }
+        }
+        items.into_iter().map(|item| item.extract()).collect()
+    }
+}
+
+// MARK: EventCountImage
+
+/// The count of events of each pixel, colored with a sequential colormap.
+#[derive(Clone)]
+pub struct EventCountImage {
+    grid: PixelGrid,
+    counts: Vec<u32>,
+    colormap: Colormap,
+    /// The count at the top of the colormap, `None` follows the largest count so far
+    max: Option<u32>,
+    show_labels: bool,
+}
+
+impl EventCountImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            grid: PixelGrid::new(width, height),
+            counts: vec![0; width * height],
+            colormap: Colormap::Inferno,
+            max: None,
+            show_labels: false,
+        }
+    }
+    pub fn fit_in(&mut self, center: DVec3, width: f64, height: f64) -> &mut Self {
+        self.grid.fit_in(center, width, height);
+        self
+    }
+    pub fn set_colormap(&mut self, colormap: Colormap) -> &mut Self {
+        self.colormap = colormap;
+        self
+    }
+    pub fn set_max(&mut self, max: Option<u32>) -> &mut Self {
+        self.max = max;
+        self
+    }
+    /// Show the count on each pixel that has events.
+    pub fn set_labels(&mut self, show_labels: bool) -> &mut Self {
+        self.show_labels = show_labels;
+        self
+    }
+    /// The count of `(x, y)`, `None` if it is not on the image.
+    pub fn count(&self, x: usize, y: usize) -> Option<u32> {
+        Some(self.counts[self.grid.geometry().index_of(x, y)?])
+    }
+    /// The count at the top of the colormap, at least 1.
+    pub fn max_count(&self) -> u32 {
+        self.max
+            .unwrap_or_else(|| self.counts.iter().copied().max().unwrap_or(0))
+            .max(1)
+    }
+}
+
+/// The position of `count` on a sequential colormap whose top is `max`.
+fn count_level(count: u32, max: u32) -> f32 {
+    count as f32 / max as f32
+}
+
+impl EventSink for EventCountImage {
+    fn geometry(&self) -> SensorGeometry {
+        self.grid.geometry()
+    }
+    fn accept(&mut self, event: &Event) -> Result<(), OutOfBounds> {
+        let idx = self.grid.geometry().index(event)?;
+        self.counts[idx] += 1;
+        Ok(())
+    }
+}
+
+impl_grid_traits!(EventCountImage);
+
+impl Extract for EventCountImage {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let max = self.max_count();
+        let mut items = vec![self.grid.background(self.colormap.sample(0.0))];
+        for (idx, &count) in self.counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let (x, y) = (idx % self.grid.width, idx / self.grid.width);
+            let value = count_level(count, max);
+            items.push(self.grid.pixel(x, y, self.colormap.sample(value)));
+            if self.show_labels {
+                items.extend(self.grid.label(&count.to_string(), x, y));
+            }
+        }
+        items.into_iter().map(|item| item.extract()).collect()
+    }
+}
+
+// MARK: PolaritySumFrame
+
+/// The count of ON events minus the count of OFF events of each pixel, colored with a
+/// diverging colormap whose neutral center is a sum of zero.
+#[derive(Clone)]
+pub struct PolaritySumFrame {
+    grid: PixelGrid,
+    sums: Vec<i32>,
+    colormap: Colormap,
+    /// The absolute sum at the ends of the colormap, `None` follows the largest one so far
+    max: Option<u32>,
+    show_labels: bool,
+}
+
+impl PolaritySumFrame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            grid: PixelGrid::new(width, height),
+            sums: vec![0; width * height],
+            colormap: Colormap::CoolWarm,
+            max: None,
+            show_labels: false,
+        }
+    }
+    pub fn fit_in(&mut self, center: DVec3, width: f64, height: f64) -> &mut Self {
+        self.grid.fit_in(center, width, height);
+        self
+    }
+    /// A sequential colormap works too, with OFF at the bottom and ON at the top.
+    pub fn set_colormap(&mut self, colormap: Colormap) -> &mut Self {
+        self.colormap = colormap;
+        self
+    }
+    pub fn set_max(&mut self, max: Option<u32>) -> &mut Self {
+        self.max = max;
+        self
+    }
+    /// Show the sum on each pixel whose sum isn't zero.
+    pub fn set_labels(&mut self, show_labels: bool) -> &mut Self {
+        self.show_labels = show_labels;
+        self
+    }
+    /// The sum of `(x, y)`, `None` if it is not on the frame.
+    pub fn sum(&self, x: usize, y: usize) -> Option<i32> {
+        Some(self.sums[self.grid.geometry().index_of(x, y)?])
+    }
+    /// The absolute sum at the ends of the colormap, at least 1.
+    pub fn max_sum(&self) -> u32 {
+        self.max
+            .unwrap_or_else(|| {
+                self.sums
+                    .iter()
+                    .map(|sum| sum.unsigned_abs())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .max(1)
+    }
+}
+
+/// The position of `sum` on a diverging colormap whose ends are `-max` and `max`.
+fn sum_level(sum: i32, max: u32) -> f32 {
+    0.5 + sum as f32 / max as f32 / 2.0
+}
+
+impl EventSink for PolaritySumFrame {
+    fn geometry(&self) -> SensorGeometry {
+        self.grid.geometry()
+    }
+    fn accept(&mut self, event: &Event) -> Result<(), OutOfBounds> {
+        let idx = self.grid.geometry().index(event)?;
+        self.sums[idx] += match event.polarity {
+            Polarity::On => 1,
+            Polarity::Off => -1,
+        };
+        Ok(())
+    }
+}
+
+impl_grid_traits!(PolaritySumFrame);
+
+impl Extract for PolaritySumFrame {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let max = self.max_sum();
+        let mut items = vec![self.grid.background(self.colormap.sample(0.5))];
+        for (idx, &sum) in self.sums.iter().enumerate() {
+            if sum == 0 {
+                continue;
+            }
+            let (x, y) = (idx % self.grid.width, idx / self.grid.width);
+            let value = sum_level(sum, max);
+            items.push(self.grid.pixel(x, y, self.colormap.sample(value)));
+            if self.show_labels {
+                items.extend(self.grid.label(&format!("{sum:+}"), x, y));
+            }
+        }
+        items.into_iter().map(|item| item.extract()).collect()
+    }
+}
+
+// MARK: VoxelGrid
+
+/// The events of a time range split into `bins` temporal bins, each a polarity-sum frame.
+///
+/// An event adds its polarity (+1 / -1) to the two bins around its normalized time, weighted
+/// by the distance to them, like the voxel grid of E-RAFT and EV-FlowNet.
+///
+/// The bins are drawn as layers stacked upwards from the earliest one, each layer is the
+/// image laid down in an oblique projection, with the rows going back and to the right.
+#[derive(Clone)]
+pub struct VoxelGrid {
+    width: usize,
+    height: usize,
+    bins: usize,
+    /// The first event time of the range
+    start: u64,
+    /// The length of the range in event time, the events out of the range are ignored
+    duration: u64,
+    /// `bins` frames of `width * height`
+    values: Vec<f32>,
+    center: DVec3,
+    pixel_size: f64,
+    colormap: Colormap,
+}
+
+/// The horizontal and vertical offset of a row going back, in pixels
+const DEPTH_SKEW: DVec3 = DVec3::new(0.5, 0.35, 0.0);
+/// The distance between two layers, relative to the projected height of a layer
+const LAYER_GAP: f64 = 0.6;
+
+impl VoxelGrid {
+    /// Placed like [`PixelGrid::new`].
+    ///
+    /// Panics if `duration` is 0.
+    pub fn new(width: usize, height: usize, bins: usize, start: u64, duration: u64) -> Self {
+        assert!(
+            duration > 0,
+            "a voxel grid needs a time range, got a duration of 0"
+        );
+        let bins = bins.max(1);
+        let mut grid = Self {
+            width,
+            height,
+            bins,
+            start,
+            duration,
+            values: vec![0.0; bins * width * height],
+            center: DVec3::ZERO,
+            pixel_size: 1.0,
+            colormap: Colormap::CoolWarm,
+        };
+        grid.fit_in(DVec3::ZERO, f64::INFINITY, FRAME_SIZE.y);
+        grid
+    }
+    /// The size of the drawing for a pixel size of 1.0.
+    fn unit_size(&self) -> (f64, f64) {
+        let depth = self.height as f64 * DEPTH_SKEW;
+        let layer_height = depth.y;
+        (
+            self.width as f64 + depth.x,
+            layer_height * (1.0 + LAYER_GAP * (self.bins - 1) as f64),
+        )
+    }
+    /// Fit the layers into the `width` x `height` box centered on `center`.
+    pub fn fit_in(&mut self, center: DVec3, width: f64, height: f64) -> &mut Self {
+        let (unit_width, unit_height) = self.unit_size();
+        self.center = center;
+        self.pixel_size =
+            (width / unit_width.max(f64::EPSILON)).min(height / unit_height.max(f64::EPSILON));
+        self
+    }
+    pub fn set_colormap(&mut self, colormap: Colormap) -> &mut Self {
+        self.colormap = colormap;
+        self
+    }
+    pub fn bins(&self) -> usize {
+        self.bins
+    }
+    pub fn geometry(&self) -> SensorGeometry {
+        SensorGeometry::new(self.width, self.height)
+    }
+    /// The value of `(x, y)` in `bin`, `None` if either is out of the grid.
+    pub fn value(&self, bin: usize, x: usize, y: usize) -> Option<f32> {
+        let idx = self.geometry().index_of(x, y)?;
+        (bin < self.bins).then(|| self.values[bin * self.geometry().pixel_cnt() + idx])
+    }
+    /// The scene position of the point `(px, py)` of the image on the layer of `bin`, in
+    /// pixels from the top left corner.
+    fn project(&self, bin: usize, px: f64, py: f64) -> DVec3 {
+        let (unit_width, unit_height) = self.unit_size();
+        let layer_height = self.height as f64 * DEPTH_SKEW.y;
+        let bottom_left = dvec3(-unit_width / 2.0, -unit_height / 2.0, 0.0);
+        let pos = bottom_left
+            + DVec3::Y * bin as f64 * layer_height * LAYER_GAP
+            + DVec3::X * px
+            + DEPTH_SKEW * (self.height as f64 - py);
+        self.center + pos * self.pixel_size
+    }
+    fn quad(&self, bin: usize, x: f64, y: f64, w: f64, h: f64) -> Polygon {
+        Polygon::new(vec![
+            self.project(bin, x, y + h),
+            self.project(bin, x + w, y + h),
+            self.project(bin, x + w, y),
+            self.project(bin, x, y),
+        ])
+    }
+}
+
+impl EventSink for VoxelGrid {
+    fn geometry(&self) -> SensorGeometry {
+        VoxelGrid::geometry(self)
+    }
+    /// An event out of the time range is ignored, it is not an error.
+    fn accept(&mut self, event: &Event) -> Result<(), OutOfBounds> {
+        let idx = self.geometry().index(event)?;
+        if !(self.start..self.start.saturating_add(self.duration)).contains(&event.t) {
+            return Ok(());
+        }
+        let sign = match event.polarity {
+            Polarity::On => 1.0,
+            Polarity::Off => -1.0,
+        };
+        let pos = (event.t - self.start) as f32 / self.duration as f32 * (self.bins - 1) as f32;
+        let bin = pos.floor() as usize;
+        let frac = pos - bin as f32;
+        let pixel_cnt = self.geometry().pixel_cnt();
+        self.values[bin * pixel_cnt + idx] += sign * (1.0 - frac);
+        if bin + 1 < self.bins {
+            self.values[(bin + 1) * pixel_cnt + idx] += sign * frac;
+        }
+        Ok(())
+    }
+}
+
+impl BoundingBox for VoxelGrid {
+    fn get_bounding_box(&self) -> [DVec3; 3] {
+        let (unit_width, unit_height) = self.unit_size();
+        let half = dvec3(unit_width, unit_height, 0.0) * self.pixel_size / 2.0;
+        [self.center - half, self.center, self.center + half]
+    }
+}
+
+impl Shift for VoxelGrid {
+    fn shift(&mut self, shift: DVec3) -> &mut Self {
+        self.center += shift;
+        self
+    }
+}
+
+impl Scale for VoxelGrid {
+    /// See [`square_scale`].
+    fn scale_by_anchor(&mut self, scale: DVec3, anchor: Anchor) -> &mut Self {
+        let (center, factor) = square_scale(self, self.center, scale, anchor);
+        self.center = center;
+        self.pixel_size *= factor;
+        self
+    }
+}
+
+impl Extract for VoxelGrid {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let max = self
+            .values
+            .iter()
+            .fold(0.0f32, |max, value| max.max(value.abs()))
+            .max(f32::EPSILON);
+        let (width, height) = (self.width as f64, self.height as f64);
+        let mut items = vec![];
+        // From the bottom layer up and from the back row to the front one, so that the
+        // nearer pixels are drawn over the farther ones.
+        for bin in 0..self.bins {
+            items.push(self.quad(bin, 0.0, 0.0, width, height).with(|layer| {
+                layer
+                    .set_fill_color(manim::GREY_E.with_alpha(0.25))
+                    .set_stroke_color(manim::WHITE.with_alpha(0.6));
+            }));
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let value = self.values[(bin * self.height + y) * self.width + x] / max;
+                    if value == 0.0 {
+                        continue;
+                    }
+                    let color = self.colormap.sample(0.5 + value / 2.0);
+                    items.push(self.quad(bin, x as f64, y as f64, 1.0, 1.0).with(|pixel| {
+                        pixel
+                            .set_fill_color(color.with_alpha(0.2 + 0.6 * value.abs()))
+                            .set_stroke_color(color.with_alpha(0.0));
+                    }));
+                }
+            }
+        }
+        items
+            .into_iter()
+            .map(|item| VItem::from(item).extract())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::OutOfBoundsPolicy;
+
+    fn on(t: u64, x: u16, y: u16) -> Event {
+        Event::new(t, x, y, Polarity::On)
+    }
+
+    fn off(t: u64, x: u16, y: u16) -> Event {
+        Event::new(t, x, y, Polarity::Off)
+    }
+
+    #[test]
+    fn counts_per_pixel() {
+        let mut image = EventCountImage::new(3, 2);
+        let events = [on(0, 0, 0), off(1, 0, 0), on(2, 2, 1), on(3, 0, 0)];
+        assert_eq!(image.feed(&events, OutOfBoundsPolicy::Error), Ok(0));
+        assert_eq!(image.count(0, 0), Some(3));
+        assert_eq!(image.count(2, 1), Some(1));
+        assert_eq!(image.count(1, 0), Some(0));
+        assert_eq!(image.count(3, 0), None);
+        assert_eq!(image.max_count(), 3);
+    }
+
+    #[test]
+    fn count_levels() {
+        let mut image = EventCountImage::new(2, 2);
+        // An empty image doesn't divide by zero
+        assert_eq!(image.max_count(), 1);
+        let events = [on(0, 0, 0), on(1, 0, 0), on(2, 0, 0), on(3, 1, 1)];
+        image.feed(&events, OutOfBoundsPolicy::Error).unwrap();
+        let max = image.max_count();
+        assert_eq!(count_level(3, max), 1.0);
+        assert_eq!(count_level(1, max), 1.0 / 3.0);
+        image.set_max(Some(6));
+        assert_eq!(count_level(3, image.max_count()), 0.5);
+    }
+
+    #[test]
+    fn signed_polarity_sums() {
+        let mut frame = PolaritySumFrame::new(2, 2);
+        let events = [
+            on(0, 0, 0),
+            on(1, 0, 0),
+            off(2, 1, 0),
+            off(3, 1, 0),
+            on(4, 1, 1),
+        ];
+        frame.feed(&events, OutOfBoundsPolicy::Error).unwrap();
+        frame.accept(&off(5, 1, 1)).unwrap();
+        assert_eq!(frame.sum(0, 0), Some(2));
+        assert_eq!(frame.sum(1, 0), Some(-2));
+        assert_eq!(frame.sum(1, 1), Some(0));
+        assert_eq!(frame.sum(0, 2), None);
+        assert_eq!(frame.max_sum(), 2);
+    }
+
+    #[test]
+    fn sum_levels_are_centered_on_zero() {
+        let mut frame = PolaritySumFrame::new(2, 1);
+        assert_eq!(frame.max_sum(), 1);
+        for t in 0..4 {
+            frame.accept(&off(t, 0, 0)).unwrap();
+        }
+        frame.accept(&on(4, 1, 0)).unwrap();
+        let max = frame.max_sum();
+        assert_eq!(max, 4);
+        assert_eq!(sum_level(0, max), 0.5);
+        assert_eq!(sum_level(-4, max), 0.0);
+        assert_eq!(sum_level(4, max), 1.0);
+        assert_eq!(sum_level(1, max), 0.625);
+        frame.set_max(Some(8));
+        assert_eq!(sum_level(-4, frame.max_sum()), 0.25);
+    }
+
+    #[test]
+    fn voxel_bin_weights() {
+        // Bins at t = 100, 150 and 200
+        let mut grid = VoxelGrid::new(2, 1, 3, 100, 100);
+        grid.accept(&on(100, 0, 0)).unwrap();
+        assert_eq!(grid.value(0, 0, 0), Some(1.0));
+        assert_eq!(grid.value(1, 0, 0), Some(0.0));
+
+        // Exactly on the boundary of the middle bin
+        grid.accept(&off(150, 1, 0)).unwrap();
+        assert_eq!(grid.value(0, 1, 0), Some(0.0));
+        assert_eq!(grid.value(1, 1, 0), Some(-1.0));
+        assert_eq!(grid.value(2, 1, 0), Some(0.0));
+
+        // A quarter of the way from the middle bin to the last one
+        grid.accept(&on(175, 0, 0)).unwrap();
+        assert_eq!(grid.value(1, 0, 0), Some(0.5));
+        assert_eq!(grid.value(2, 0, 0), Some(0.5));
+
+        assert_eq!(grid.value(3, 0, 0), None);
+        assert_eq!(grid.value(0, 2, 0), None);
+    }
+
+    #[test]
+    fn voxel_last_bin() {
+        let mut grid = VoxelGrid::new(1, 1, 3, 0, 100);
+        grid.accept(&on(99, 0, 0)).unwrap();
+        let (middle, last) = (grid.value(1, 0, 0).unwrap(), grid.value(2, 0, 0).unwrap());
+        assert!((middle - 0.02).abs() < 1e-6);
+        assert!((last - 0.98).abs() < 1e-6);
+        assert_eq!(grid.value(0, 0, 0), Some(0.0));
+
+        // A single bin takes the whole weight of every event
+        let mut grid = VoxelGrid::new(1, 1, 1, 0, 100);
+        grid.accept(&on(10, 0, 0)).unwrap();
+        grid.accept(&on(90, 0, 0)).unwrap();
+        assert_eq!(grid.value(0, 0, 0), Some(2.0));
+    }
+
+    #[test]
+    fn voxel_ignores_times_out_of_the_range() {
+        let mut grid = VoxelGrid::new(1, 1, 2, 100, 100);
+        assert_eq!(grid.accept(&on(99, 0, 0)), Ok(()));
+        assert_eq!(grid.accept(&on(200, 0, 0)), Ok(()));
+        assert_eq!(grid.accept(&on(1000, 0, 0)), Ok(()));
+        assert_eq!(grid.value(0, 0, 0), Some(0.0));
+        assert_eq!(grid.value(1, 0, 0), Some(0.0));
+    }
+
+    #[test]
+    fn voxel_range_may_end_past_u64() {
+        let mut grid = VoxelGrid::new(1, 1, 2, u64::MAX - 10, 100);
+        grid.accept(&on(u64::MAX - 10, 0, 0)).unwrap();
+        assert_eq!(grid.value(0, 0, 0), Some(1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "duration of 0")]
+    fn voxel_range_is_not_empty() {
+        VoxelGrid::new(1, 1, 2, 0, 0);
+    }
+
+    #[test]
+    fn out_of_bounds_policy() {
+        let events = [on(0, 0, 0), on(1, 2, 0), on(2, 1, 1), on(3, 0, 5)];
+
+        let mut image = EventCountImage::new(2, 2);
+        assert_eq!(image.feed(&events, OutOfBoundsPolicy::Drop), Ok(2));
+        assert_eq!(image.count(0, 0), Some(1));
+        assert_eq!(image.count(1, 1), Some(1));
+
+        let mut image = EventCountImage::new(2, 2);
+        let err = image.feed(&events, OutOfBoundsPolicy::Error).unwrap_err();
+        assert_eq!(err.event, events[1]);
+        // The events before the error are kept, the ones after it are not fed
+        assert_eq!(image.count(0, 0), Some(1));
+        assert_eq!(image.count(1, 1), Some(0));
+
+        let mut frame = PolaritySumFrame::new(2, 2);
+        assert_eq!(frame.feed(&events, OutOfBoundsPolicy::Drop), Ok(2));
+        assert!(frame.accept(&off(4, 2, 2)).is_err());
+        assert_eq!(frame.sum(1, 1), Some(1));
+
+        // Out of the sensor is an error even out of the time range of a voxel grid
+        let mut grid = VoxelGrid::new(2, 2, 2, 0, 10);
+        assert_eq!(grid.feed(&events, OutOfBoundsPolicy::Drop), Ok(2));
+        assert_eq!(
+            grid.accept(&on(100, 2, 0)).unwrap_err().geometry,
+            SensorGeometry::new(2, 2)
+        );
+    }
+}
@@ -0,0 +1,465 @@
+//! Synthetic event streams with ground-truth labels.
+//!
+//! The signal generators move a bright pattern over a dark background and simulate an ideal
+//! sensor: a pixel fires an ON event when the pattern turns it bright and an OFF event when it
+//! turns dark again. Every generator is deterministic for a seed and a sensor size.
+
+use std::f64::consts::PI;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::event::{Event, Polarity};
+
+/// An event with whether it comes from the signal or from noise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LabeledEvent {
+    pub event: Event,
+    pub signal: bool,
+}
+
+pub trait Generator {
+    /// The events in `0..duration` on a `width` x `height` sensor, sorted by time.
+    fn generate(&self, seed: u64, width: usize, height: usize, duration: u64) -> Vec<LabeledEvent>;
+}
+
+/// Mixing the streams of two generators, the second one gets a different seed.
+impl<A: Generator, B: Generator> Generator for (A, B) {
+    fn generate(&self, seed: u64, width: usize, height: usize, duration: u64) -> Vec<LabeledEvent> {
+        let mut events = self.0.generate(seed, width, height, duration);
+        events.extend(self.1.generate(
+            seed.wrapping_add(0x9e37_79b9_7f4a_7c15),
+            width,
+            height,
+            duration,
+        ));
+        events.sort();
+        events
+    }
+}
+
+/// Sample `bright(x, y, t)` at the center of every pixel every `step`, and emit an event for
+/// each change with its time jittered within the step.
+fn simulate(
+    seed: u64,
+    width: usize,
+    height: usize,
+    duration: u64,
+    step: u64,
+    bright: impl Fn(f64, f64, f64) -> bool,
+) -> Vec<LabeledEvent> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let step = step.max(1);
+    let mut state = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| bright(x as f64 + 0.5, y as f64 + 0.5, 0.0))
+        .collect::<Vec<_>>();
+    let mut events = vec![];
+    for t in (step..duration).step_by(step as usize) {
+        for y in 0..height {
+            for x in 0..width {
+                let now = bright(x as f64 + 0.5, y as f64 + 0.5, t as f64);
+                let last = &mut state[y * width + x];
+                if now != *last {
+                    *last = now;
+                    events.push(LabeledEvent {
+                        event: Event::new(
+                            t - rng.random_range(0..step),
+                            x as u16,
+                            y as u16,
+                            Polarity::from(now),
+                        ),
+                        signal: true,
+                    });
+                }
+            }
+        }
+    }
+    events.sort();
+    events
+}
+
+/// A vertical bright bar sweeping from left to right, wrapping around.
+#[derive(Debug, Clone, Copy)]
+pub struct MovingBar {
+    /// In pixels
+    pub bar_width: f64,
+    /// In pixels per 1000 time units
+    pub speed: f64,
+    /// The sampling period of the simulation, in time units
+    pub step: u64,
+}
+
+impl MovingBar {
+    pub fn new(bar_width: f64, speed: f64) -> Self {
+        Self {
+            bar_width,
+            speed,
+            step: 1,
+        }
+    }
+    pub fn with_step(mut self, step: u64) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+impl Generator for MovingBar {
+    fn generate(&self, seed: u64, width: usize, height: usize, duration: u64) -> Vec<LabeledEvent> {
+        let period = width as f64 + self.bar_width;
+        simulate(seed, width, height, duration, self.step, |x, _, t| {
+            let left = (self.speed * t / 1000.0).rem_euclid(period) - self.bar_width;
+            (left..left + self.bar_width).contains(&x)
+        })
+    }
+}
+
+/// A disk with alternating bright and dark sectors rotating around the sensor center.
+#[derive(Debug, Clone, Copy)]
+pub struct RotatingDisk {
+    /// In pixels
+    pub radius: f64,
+    /// In radians per 1000 time units
+    pub angular_speed: f64,
+    /// The count of the bright sectors, there are as many dark ones between them
+    pub sectors: usize,
+    /// The sampling period of the simulation, in time units
+    pub step: u64,
+}
+
+impl RotatingDisk {
+    pub fn new(radius: f64, angular_speed: f64) -> Self {
+        Self {
+            radius,
+            angular_speed,
+            sectors: 2,
+            step: 1,
+        }
+    }
+    pub fn with_sectors(mut self, sectors: usize) -> Self {
+        self.sectors = sectors;
+        self
+    }
+    pub fn with_step(mut self, step: u64) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+impl Generator for RotatingDisk {
+    fn generate(&self, seed: u64, width: usize, height: usize, duration: u64) -> Vec<LabeledEvent> {
+        let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+        let sector_angle = PI / self.sectors.max(1) as f64;
+        simulate(seed, width, height, duration, self.step, |x, y, t| {
+            let (dx, dy) = (x - cx, y - cy);
+            if dx.hypot(dy) > self.radius {
+                return false;
+            }
+            let angle = dy.atan2(dx) - self.angular_speed * t / 1000.0;
+            (angle / sector_angle).floor().rem_euclid(2.0) == 0.0
+        })
+    }
+}
+
+/// A checkerboard translating with a constant velocity.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkerboard {
+    /// The size of a square, in pixels
+    pub square_size: f64,
+    /// In pixels per 1000 time units
+    pub velocity: (f64, f64),
+    /// The sampling period of the simulation, in time units
+    pub step: u64,
+}
+
+impl Checkerboard {
+    pub fn new(square_size: f64, velocity: (f64, f64)) -> Self {
+        Self {
+            square_size,
+            velocity,
+            step: 1,
+        }
+    }
+    pub fn with_step(mut self, step: u64) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+impl Generator for Checkerboard {
+    fn generate(&self, seed: u64, width: usize, height: usize, duration: u64) -> Vec<LabeledEvent> {
+        let size = self.square_size.max(f64::EPSILON);
+        simulate(seed, width, height, duration, self.step, |x, y, t| {
+            let x = ((x - self.velocity.0 * t / 1000.0) / size).floor();
+            let y = ((y - self.velocity.1 * t / 1000.0) / size).floor();
+            (x + y).rem_euclid(2.0) == 0.0
+        })
+    }
+}
+
+/// A bright square translating with a constant velocity, whose corners are known at any time.
+#[derive(Debug, Clone, Copy)]
+pub struct MovingSquare {
+    /// In pixels
+    pub size: f64,
+    /// The top left corner at time 0 in pixels, `None` centers the square on the sensor
+    pub start: Option<(f64, f64)>,
+    /// In pixels per 1000 time units
+    pub velocity: (f64, f64),
+    /// The sampling period of the simulation, in time units
+    pub step: u64,
+}
+
+impl MovingSquare {
+    pub fn new(size: f64, velocity: (f64, f64)) -> Self {
+        Self {
+            size,
+            start: None,
+            velocity,
+            step: 1,
+        }
+    }
+    pub fn with_start(mut self, x: f64, y: f64) -> Self {
+        self.start = Some((x, y));
+        self
+    }
+    pub fn with_step(mut self, step: u64) -> Self {
+        self.step = step;
+        self
+    }
+    /// The top left, top right, bottom right and bottom left corners at time `t`, in pixels,
+    /// which are the ground truth for a corner detector.
+    pub fn corners(&self, width: usize, height: usize, t: u64) -> [(f64, f64); 4] {
+        let (x0, y0) = self.start.unwrap_or((
+            (width as f64 - self.size) / 2.0,
+            (height as f64 - self.size) / 2.0,
+        ));
+        let x = x0 + self.velocity.0 * t as f64 / 1000.0;
+        let y = y0 + self.velocity.1 * t as f64 / 1000.0;
+        [
+            (x, y),
+            (x + self.size, y),
+            (x + self.size, y + self.size),
+            (x, y + self.size),
+        ]
+    }
+}
+
+impl Generator for MovingSquare {
+    fn generate(&self, seed: u64, width: usize, height: usize, duration: u64) -> Vec<LabeledEvent> {
+        simulate(seed, width, height, duration, self.step, |x, y, t| {
+            let [(left, top), _, (right, bottom), _] = self.corners(width, height, t as u64);
+            (left..right).contains(&x) && (top..bottom).contains(&y)
+        })
+    }
+}
+
+/// A round LED that is on for the first half of each period.
+#[derive(Debug, Clone, Copy)]
+pub struct BlinkingLed {
+    /// The center in pixels, `None` is the sensor center
+    pub center: Option<(f64, f64)>,
+    /// In pixels
+    pub radius: f64,
+    /// In time units
+    pub period: u64,
+    /// The sampling period of the simulation, in time units
+    pub step: u64,
+}
+
+impl BlinkingLed {
+    pub fn new(radius: f64, period: u64) -> Self {
+        Self {
+            center: None,
+            radius,
+            period,
+            step: 1,
+        }
+    }
+    pub fn with_center(mut self, x: f64, y: f64) -> Self {
+        self.center = Some((x, y));
+        self
+    }
+    pub fn with_step(mut self, step: u64) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+impl Generator for BlinkingLed {
+    fn generate(&self, seed: u64, width: usize, height: usize, duration: u64) -> Vec<LabeledEvent> {
+        let (cx, cy) = self
+            .center
+            .unwrap_or((width as f64 / 2.0, height as f64 / 2.0));
+        let period = self.period.max(2) as f64;
+        simulate(seed, width, height, duration, self.step, |x, y, t| {
+            (x - cx).hypot(y - cy) <= self.radius && t.rem_euclid(period) < period / 2.0
+        })
+    }
+}
+
+/// Background activity, events uniformly distributed in space and time with random
+/// polarities, all labeled as noise.
+#[derive(Debug, Clone, Copy)]
+pub struct BackgroundActivity {
+    /// The expected count of events per pixel per 1000 time units
+    pub rate: f64,
+}
+
+impl BackgroundActivity {
+    pub fn new(rate: f64) -> Self {
+        Self { rate }
+    }
+}
+
+impl Generator for BackgroundActivity {
+    fn generate(&self, seed: u64, width: usize, height: usize, duration: u64) -> Vec<LabeledEvent> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        if width == 0 || height == 0 || duration == 0 {
+            return vec![];
+        }
+        let expected = self.rate.max(0.0) * (width * height) as f64 * duration as f64 / 1000.0;
+        // Rounding the fraction randomly keeps the expected count for low rates.
+        let cnt = expected.floor() as usize + rng.random_bool(expected.fract()) as usize;
+        let mut events = (0..cnt)
+            .map(|_| LabeledEvent {
+                event: Event::new(
+                    rng.random_range(0..duration),
+                    rng.random_range(0..width) as u16,
+                    rng.random_range(0..height) as u16,
+                    Polarity::from(rng.random::<bool>()),
+                ),
+                signal: false,
+            })
+            .collect::<Vec<_>>();
+        events.sort();
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 24;
+    const HEIGHT: usize = 16;
+    const DURATION: u64 = 2000;
+
+    /// The signal generators, with a step so that the times are jittered by the seed.
+    fn signal_generators() -> Vec<(&'static str, Box<dyn Generator>)> {
+        vec![
+            ("bar", Box::new(MovingBar::new(3.0, 20.0).with_step(7))),
+            ("disk", Box::new(RotatingDisk::new(6.0, 2.0).with_step(7))),
+            (
+                "checkerboard",
+                Box::new(Checkerboard::new(4.0, (10.0, 5.0)).with_step(7)),
+            ),
+            (
+                "square",
+                Box::new(MovingSquare::new(5.0, (8.0, 4.0)).with_step(7)),
+            ),
+            ("led", Box::new(BlinkingLed::new(4.0, 300).with_step(7))),
+        ]
+    }
+
+    fn assert_well_formed(name: &str, events: &[LabeledEvent]) {
+        assert!(!events.is_empty(), "{name} has no events");
+        for pair in events.windows(2) {
+            assert!(pair[0].event.t <= pair[1].event.t, "{name} is not sorted");
+        }
+        for LabeledEvent { event, .. } in events {
+            assert!(event.t < DURATION, "{name}: {event:?} is too late");
+            assert!(
+                (event.x as usize) < WIDTH && (event.y as usize) < HEIGHT,
+                "{name}: {event:?} is off the sensor"
+            );
+        }
+    }
+
+    #[test]
+    fn signal_generators_are_deterministic_and_labeled() {
+        for (name, generator) in signal_generators() {
+            let events = generator.generate(11, WIDTH, HEIGHT, DURATION);
+            assert_well_formed(name, &events);
+            assert!(events.iter().all(|e| e.signal), "{name} has noise");
+            assert_eq!(
+                events,
+                generator.generate(11, WIDTH, HEIGHT, DURATION),
+                "{name} changed for the same seed"
+            );
+            assert_ne!(
+                events,
+                generator.generate(12, WIDTH, HEIGHT, DURATION),
+                "{name} didn't change with the seed"
+            );
+        }
+    }
+
+    #[test]
+    fn background_activity_is_noise_at_its_rate() {
+        let noise = BackgroundActivity::new(0.5);
+        let events = noise.generate(11, WIDTH, HEIGHT, DURATION);
+        assert_well_formed("noise", &events);
+        assert!(events.iter().all(|e| !e.signal));
+        assert_eq!(events, noise.generate(11, WIDTH, HEIGHT, DURATION));
+        assert_ne!(events, noise.generate(12, WIDTH, HEIGHT, DURATION));
+        // 0.5 events per pixel per 1000 time units.
+        assert_eq!(events.len(), WIDTH * HEIGHT);
+        assert!(noise.generate(11, WIDTH, HEIGHT, 0).is_empty());
+        assert!(noise.generate(11, 0, HEIGHT, DURATION).is_empty());
+    }
+
+    #[test]
+    fn mix_keeps_the_labels_of_both_streams() {
+        let (signal, noise) = (
+            MovingBar::new(3.0, 20.0).with_step(7),
+            BackgroundActivity::new(0.5),
+        );
+        let mixed = (signal, noise).generate(11, WIDTH, HEIGHT, DURATION);
+        assert_well_formed("mix", &mixed);
+        assert_eq!(mixed, (signal, noise).generate(11, WIDTH, HEIGHT, DURATION));
+
+        let signal_events = signal.generate(11, WIDTH, HEIGHT, DURATION);
+        let (mixed_signal, mixed_noise): (Vec<_>, Vec<_>) =
+            mixed.iter().copied().partition(|e| e.signal);
+        assert_eq!(mixed_signal, signal_events);
+        // The noise gets a seed of its own.
+        assert_eq!(mixed_noise.len(), WIDTH * HEIGHT);
+        assert_ne!(mixed_noise, noise.generate(11, WIDTH, HEIGHT, DURATION));
+    }
+
+    #[test]
+    fn moving_square_corners() {
+        let square = MovingSquare::new(4.0, (5.0, 2.5));
+        assert_eq!(
+            square.corners(10, 8, 0),
+            [(3.0, 2.0), (7.0, 2.0), (7.0, 6.0), (3.0, 6.0)]
+        );
+        assert_eq!(
+            square.with_start(1.0, 0.0).corners(10, 8, 400),
+            [(3.0, 1.0), (7.0, 1.0), (7.0, 5.0), (3.0, 5.0)]
+        );
+    }
+
+    #[test]
+    fn moving_square_fires_on_its_edges() {
+        // 0.1 pixels per step to the right, so the events are within 0.1 pixels of the edges.
+        let square = MovingSquare::new(6.0, (10.0, 0.0))
+            .with_start(2.0, 3.0)
+            .with_step(10);
+        let events = square.generate(7, 20, 12, 1000);
+        // Every row of the square crosses 10 pixel centers with each edge.
+        assert_eq!(events.len(), 2 * 6 * 10);
+        for LabeledEvent { event, signal } in events {
+            assert!(signal);
+            let [(left, top), (right, _), (_, bottom), _] = square.corners(20, 12, event.t);
+            let edge = match event.polarity {
+                Polarity::On => right,
+                Polarity::Off => left,
+            };
+            let (x, y) = (event.x as f64 + 0.5, event.y as f64 + 0.5);
+            assert!((x - edge).abs() <= 0.1 + 1e-9, "{event:?} is off its edge");
+            assert!((top..bottom).contains(&y), "{event:?} is off the square");
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! A cache of glyph outlines for assembling numeric labels without compiling typst.
+//!
+//! Compiling a label with typst and parsing its svg for every changed cell dominates the
+//! extraction of a busy scene, while the labels only use a handful of characters. The glyphs
+//! are compiled once, and a label is assembled by placing the cached outlines side by side.
+
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+use ranim::{
+    components::Anchor,
+    glam::{DVec3, dvec3},
+    items::{
+        Group,
+        vitem::{VItem, svg::SvgItem, typst::typst_svg},
+    },
+    prelude::*,
+};
+
+/// The characters compiled up front, other characters are compiled the first time they are used.
+pub const PRELOADED: &str = "0123456789.,-+%eµmnskHz";
+
+/// The gap between two glyphs, in digit heights
+const TRACKING: f64 = 0.12;
+/// The width of a space, in digit heights
+const SPACE_WIDTH: f64 = 0.35;
+
+/// The outline of a character, the digit height is 1.0, the baseline is at y = 0.0 and the
+/// left edge of the ink is at x = 0.0.
+#[derive(Clone)]
+pub struct Glyph {
+    pub outline: Vec<VItem>,
+    pub width: f64,
+}
+
+impl Glyph {
+    /// Compile the glyph next to a reference `1`, whose ink goes from the baseline to the
+    /// digit height, so every glyph shares the same baseline and scale.
+    fn compile(c: char) -> Self {
+        if c.is_whitespace() {
+            return Self {
+                outline: vec![],
+                width: SPACE_WIDTH,
+            };
+        }
+        let mut items = Group::<VItem>::from(SvgItem::new(typst_svg(&markup(c)))).0;
+        items.sort_by(|a, b| {
+            a.get_bounding_box()[0]
+                .x
+                .total_cmp(&b.get_bounding_box()[0].x)
+        });
+        let reference = items.remove(0);
+        let [min, _, max] = reference.get_bounding_box();
+        let height = (max.y - min.y).max(f64::EPSILON);
+        if items.is_empty() {
+            // Nothing visible, like a character that the font doesn't have.
+            return Self {
+                outline: vec![],
+                width: SPACE_WIDTH,
+            };
+        }
+        let [glyph_min, _, glyph_max] = items.get_bounding_box();
+        items
+            .shift(DVec3::new(-glyph_min.x, -min.y, 0.0))
+            .scale_by_anchor(DVec3::splat(1.0 / height), Anchor::ORIGIN);
+        Self {
+            outline: items,
+            width: (glyph_max.x - glyph_min.x) / height,
+        }
+    }
+}
+
+/// The typst markup of `c` after the reference `1`, as a string literal so that markup
+/// characters like `*`, `#` or `$` are drawn as they are.
+fn markup(c: char) -> String {
+    let escaped = match c {
+        '"' | '\\' => format!("\\{c}"),
+        c => c.to_string(),
+    };
+    format!("1 #\"{escaped}\"")
+}
+
+#[derive(Default)]
+pub struct GlyphCache {
+    glyphs: HashMap<char, Glyph>,
+}
+
+impl GlyphCache {
+    pub fn new() -> Self {
+        let mut cache = Self::default();
+        cache.preload(PRELOADED);
+        cache
+    }
+    /// The cache shared by the whole process.
+    pub fn global() -> &'static RwLock<GlyphCache> {
+        static CACHE: OnceLock<RwLock<GlyphCache>> = OnceLock::new();
+        CACHE.get_or_init(|| RwLock::new(GlyphCache::new()))
+    }
+    pub fn preload(&mut self, chars: &str) {
+        self.preload_with(chars, Glyph::compile);
+    }
+    fn preload_with(&mut self, chars: &str, mut compile: impl FnMut(char) -> Glyph) {
+        for c in chars.chars() {
+            self.glyphs.entry(c).or_insert_with(|| compile(c));
+        }
+    }
+    pub fn contains_all(&self, text: &str) -> bool {
+        text.chars().all(|c| self.glyphs.contains_key(&c))
+    }
+    /// Assemble `text` from the cached glyphs, with the digit height of 1.0, the baseline at
+    /// y = 0.0 and the left edge at x = 0.0.
+    ///
+    /// Panics if a character is not cached, see [`GlyphCache::preload`].
+    pub fn label(&self, text: &str) -> Group<VItem> {
+        let mut x = 0.0;
+        let mut items = vec![];
+        for c in text.chars() {
+            let glyph = &self.glyphs[&c];
+            items.extend(glyph.outline.iter().cloned().map(|mut item| {
+                item.shift(DVec3::X * x);
+                item
+            }));
+            x += glyph.width + TRACKING;
+        }
+        Group(items)
+    }
+    /// The [`GlyphCache::label`] of `text`, placed like [`glyph_label`].
+    fn placed_label(&self, text: &str, height: f64, center: DVec3) -> Group<VItem> {
+        let mut label = self.label(text);
+        if !label.is_empty() {
+            label.scale_by_anchor(DVec3::splat(height), Anchor::ORIGIN);
+            let [min, _, max] = label.get_bounding_box();
+            label.shift(center - dvec3((min.x + max.x) / 2.0, height / 2.0, 0.0));
+        }
+        label
+    }
+}
+
+/// Assemble a label with the [`GlyphCache::global`] cache, compiling the missing glyphs.
+///
+/// The label is `height` high in digit height, its ink is centered horizontally on `center`
+/// and its digits are centered vertically on it, so labels don't jump around with their signs
+/// and decimal points.
+pub fn glyph_label(text: &str, height: f64, center: DVec3) -> Group<VItem> {
+    let cache = GlyphCache::global();
+    if !cache.read().unwrap().contains_all(text) {
+        cache.write().unwrap().preload(text);
+    }
+    cache.read().unwrap().placed_label(text, height, center)
+}
+
+#[cfg(test)]
+mod tests {
+    use ranim::items::vitem::geometry::Rectangle;
+
+    use super::*;
+
+    /// A glyph whose ink is a `width` wide box from `bottom` to `top`.
+    fn glyph(width: f64, bottom: f64, top: f64) -> Glyph {
+        let outline = VItem::from(Rectangle::new(width, top - bottom).with(|rect| {
+            rect.put_center_on(dvec3(width / 2.0, (bottom + top) / 2.0, 0.0));
+        }));
+        Glyph {
+            outline: vec![outline],
+            width,
+        }
+    }
+
+    /// A cache of `1`, `2`, `-` and a space, without compiling typst.
+    fn cache() -> GlyphCache {
+        let mut cache = GlyphCache::default();
+        cache.preload_with("12- ", |c| match c {
+            '1' => glyph(0.5, 0.0, 1.0),
+            '2' => glyph(0.6, 0.0, 1.0),
+            '-' => glyph(0.4, 0.45, 0.55),
+            _ => Glyph {
+                outline: vec![],
+                width: SPACE_WIDTH,
+            },
+        });
+        cache
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(markup('7'), r#"1 #"7""#);
+        assert_eq!(markup('*'), r#"1 #"*""#);
+        assert_eq!(markup('"'), r#"1 #"\"""#);
+        assert_eq!(markup('\\'), r#"1 #"\\""#);
+    }
+
+    #[test]
+    fn width_is_the_sum_of_the_advances() {
+        let cache = cache();
+        let [min, _, max] = cache.label("12").get_bounding_box();
+        assert_close(min.x, 0.0);
+        assert_close(max.x, 0.5 + TRACKING + 0.6);
+
+        let [_, _, max] = cache.label("1 2").get_bounding_box();
+        assert_close(max.x, 0.5 + TRACKING + SPACE_WIDTH + TRACKING + 0.6);
+    }
+
+    #[test]
+    fn glyphs_share_the_baseline() {
+        let label = cache().label("-12");
+        let [minus, one, two] = [0, 1, 2].map(|idx| label.0[idx].get_bounding_box());
+        assert_close(minus[0].y, 0.45);
+        assert_close(minus[2].y, 0.55);
+        for digit in [one, two] {
+            assert_close(digit[0].y, 0.0);
+            assert_close(digit[2].y, 1.0);
+        }
+        assert_close(one[0].x, 0.4 + TRACKING);
+    }
+
+    #[test]
+    fn labels_are_centered() {
+        let center = dvec3(3.0, 4.0, 0.0);
+        let label = cache().placed_label("-12", 2.0, center);
+        let [min, _, max] = label.get_bounding_box();
+        assert_close((min.x + max.x) / 2.0, center.x);
+        assert_close(max.x - min.x, 2.0 * (0.4 + TRACKING + 0.5 + TRACKING + 0.6));
+        // The digits are centered, not the ink of the sign
+        let [digit_min, _, digit_max] = label.0[1].get_bounding_box();
+        assert_close(digit_min.y, 3.0);
+        assert_close(digit_max.y, 5.0);
+
+        assert!(cache().placed_label("", 2.0, center).is_empty());
+    }
+
+    #[test]
+    fn cached_glyphs_are_not_compiled_again() {
+        let mut cache = cache();
+        let mut compiled = vec![];
+        cache.preload_with("2-1", |c| {
+            compiled.push(c);
+            glyph(1.0, 0.0, 1.0)
+        });
+        assert!(compiled.is_empty());
+        cache.preload_with("1x2x", |c| {
+            compiled.push(c);
+            glyph(1.0, 0.0, 1.0)
+        });
+        assert_eq!(compiled, vec!['x']);
+        assert!(cache.contains_all("12x"));
+        assert!(!cache.contains_all("y"));
+    }
+}
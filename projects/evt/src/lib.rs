@@ -1,29 +1,74 @@
 //! Note: total 6s
 //! optimize font search: 9min -> 26s
 //! cache unchanged cell: 26s -> 25s
-use std::sync::{Arc, Mutex, OnceLock};
+use std::{f64::consts::PI, sync::Arc};
 
-use itertools::Itertools;
-use rand::{Rng, SeedableRng};
-use rand_chacha::ChaCha8Rng;
 use ranim::{
+    animation::transform::TransformAnim,
     color::palettes::manim,
     components::ScaleHint,
-    glam::{DVec3, dvec3},
-    items::{
-        Group,
-        vitem::{VItem, geometry::Square, svg::SvgItem, typst::typst_svg},
-    },
+    glam::{DQuat, DVec2, DVec3, dvec3},
+    items::vitem::{svg::SvgItem, typst::typst_svg},
     prelude::*,
-    render::primitives::{Extract, vitem::VItemPrimitive},
     timeline::TimelinesFunc,
+    utils::rate_functions::smooth,
 };
-use rayon::prelude::*;
 
-fn rng() -> Arc<Mutex<ChaCha8Rng>> {
-    static RNG: OnceLock<Arc<Mutex<ChaCha8Rng>>> = OnceLock::new();
-    RNG.get_or_init(|| Arc::new(Mutex::new(ChaCha8Rng::seed_from_u64(0))))
-        .clone()
+pub mod cloud;
+pub mod colormap;
+pub mod compare;
+pub mod corner;
+pub mod event;
+pub mod filter;
+pub mod flow;
+pub mod frame;
+pub mod generator;
+pub mod glyph;
+pub mod metrics;
+pub mod normalize;
+pub mod persistent;
+pub mod reader;
+pub mod scatter;
+pub mod schedule;
+pub mod simulator;
+pub mod time_surface;
+pub mod zoom;
+
+pub use time_surface::TimeSurface;
+
+/// The size of the frame of the default [`CameraFrame`], from the default
+/// `SceneConfig::frame_height` and the 16:9 of the default `Output`.
+pub const FRAME_SIZE: DVec2 = DVec2::new(8.0 * 16.0 / 9.0, 8.0);
+
+use cloud::EventCloud;
+use colormap::{Colorbar, Colormap};
+use compare::SurfaceComparison;
+use corner::ArcStar;
+use event::{Event, EventSink, OutOfBoundsPolicy};
+use filter::{BackgroundActivityFilter, EventFilter, KnnFilter, PassThrough};
+use flow::PlaneFit;
+use frame::{EventCountImage, FrameView, PolaritySumFrame, VoxelGrid};
+use generator::{
+    BackgroundActivity, Checkerboard, Generator, LabeledEvent, MovingBar, MovingSquare,
+    RotatingDisk,
+};
+use metrics::MetricsReadout;
+use normalize::Normalization;
+use reader::Crop;
+use scatter::EventScatter;
+use schedule::FrameScheduler;
+use simulator::{EventSimulator, GrayFrame, read_png_frames};
+use time_surface::HistoryStyle;
+use zoom::zoom_on_event;
+
+/// Show `text` as a title above the content centered on `x`.
+fn insert_title(r: &mut RanimScene, text: &str, x: f64) {
+    r.insert_and_show(SvgItem::new(typst_svg(text)).with(|title| {
+        title
+            .scale_to(ScaleHint::PorportionalY(0.35))
+            .set_fill_color(manim::WHITE)
+            .put_center_on(dvec3(x, 3.4, 0.0));
+    }));
 }
 
 #[scene]
@@ -33,197 +78,551 @@ fn denoise(r: &mut RanimScene) {
 
     let (width, height) = (10, 10);
 
-    let time_surface = TimeSurface::new(width, height);
+    let time_surface = TimeSurface::new(width, height).with(|time_surface| {
+        time_surface.set_filter(BackgroundActivityFilter::new(10));
+    });
     let r_time_surface = r.insert_and_show(time_surface);
+    let r_readout = r.insert_and_show(MetricsReadout::new(2.6, 0.3).with(|readout| {
+        readout.put_center_on(dvec3(5.6, 0.0, 0.0));
+    }));
 
-    let events = (0..640)
-        .map(|_| {
-            let rng = rng();
-            let mut rng = rng.lock().unwrap();
-            (
-                rng.random::<u32>() % 500,
-                rng.random::<u32>() % height as u32,
-                rng.random::<u32>() % width as u32,
-            )
-        })
-        .sorted()
-        .collect::<Vec<_>>();
+    // The generated events come with ground-truth labels, which are scored on the readout.
+    // Set `EVT_RECORDING` to a recording to replay its center instead of generated events.
+    let events = match std::env::var_os("EVT_RECORDING") {
+        Some(path) => {
+            let events = reader::read_events(path).unwrap();
+            let crop = Crop::centered(&events, width, height);
+            let events = crop.apply(events).take(640).collect::<Vec<_>>();
+            let t0 = events.first().map(|event| event.t).unwrap_or(0);
+            events
+                .into_iter()
+                .map(|event| {
+                    let event = Event {
+                        t: event.t - t0,
+                        ..event
+                    };
+                    (event, None)
+                })
+                .collect::<Vec<_>>()
+        }
+        None => (MovingBar::new(2.0, 24.0), BackgroundActivity::new(0.3))
+            .generate(0, width, height, 1000)
+            .into_iter()
+            .take(640)
+            .map(|labeled| (labeled.event, Some(labeled)))
+            .collect::<Vec<_>>(),
+    };
 
     let total_secs = 6.0;
-    let step = 0.2_f64.min(total_secs / events.len() as f64);
-    for event in events {
-        r.timeline_mut(&r_time_surface).update_with(|time_surface| {
-            time_surface.accept(event.0 as usize, event.2 as usize, event.1 as usize);
+    let duration = events.last().map(|(event, _)| event.t).unwrap_or(0);
+    let scheduler = FrameScheduler::fit(60, duration, total_secs);
+    scheduler.play_frames(
+        r,
+        events,
+        |(event, _)| event.t,
+        |r, events| {
+            let time_surface = r.timeline_mut(&r_time_surface);
+            time_surface.update_with(|time_surface| {
+                for (event, labeled) in events {
+                    match labeled {
+                        Some(labeled) => time_surface.accept_labeled(labeled),
+                        None => time_surface.accept(event),
+                    }
+                    .unwrap();
+                }
+            });
+            let metrics = time_surface.snapshot_ref().metrics();
+            r.timeline_mut(&r_readout).update_with(|readout| {
+                readout.set_metrics(metrics);
+            });
+        },
+    );
+    r.timelines_mut().sync();
+}
+
+#[scene]
+#[output]
+fn event_cloud(r: &mut RanimScene) {
+    let r_cam = r.insert_and_show(CameraFrame::default());
+
+    let (width, height) = (40, 30);
+    // A bar sweeping right once with some background activity.
+    let events = (
+        MovingBar::new(3.0, 4.3).with_step(10),
+        BackgroundActivity::new(0.02),
+    )
+        .generate(0, width, height, 10_000)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .collect::<Vec<_>>();
+    let r_cloud = r.insert_and_show(EventCloud::new(events, width, height));
+    r.timelines_mut().forward(1.0);
+
+    // Orbit around the y axis to reveal the time axis, the dots follow the camera.
+    let (fps, secs, angle) = (60, 4.0, -PI / 3.0);
+    let frames = (secs * fps as f64) as usize;
+    for frame in 1..=frames {
+        let alpha = smooth(frame as f64 / frames as f64);
+        let facing = DQuat::from_axis_angle(DVec3::Y, angle * alpha) * DVec3::NEG_Z;
+        r.timeline_mut(&r_cam).update_with(|cam| {
+            cam.facing = facing;
+            cam.scale = 1.0 + 0.3 * alpha;
         });
-        r.timelines_mut().forward(step);
+        r.timeline_mut(&r_cloud).update_with(|cloud| {
+            cloud.face_camera(facing);
+        });
+        r.timelines_mut().forward(1.0 / fps as f64);
     }
+    r.timelines_mut().forward(1.0);
 }
 
-struct TimeSurfaceCell {
-    start: DVec3,
-    cell_size: f64,
-    y: usize,
-    x: usize,
-    t: usize,
-    real_t: usize,
-    _need_update: Mutex<bool>,
-    _cache: Mutex<Option<Vec<VItemPrimitive>>>,
+#[scene]
+#[output]
+fn compare_filters(r: &mut RanimScene) {
+    let camera = CameraFrame::default();
+    let _r_cam = r.insert_and_show(camera.clone());
+
+    let (width, height) = (16, 16);
+    let filters: Vec<(&str, Arc<dyn EventFilter>)> = vec![
+        ("Raw", Arc::new(PassThrough)),
+        ("BA", Arc::new(BackgroundActivityFilter::new(40))),
+        ("kNN", Arc::new(KnnFilter::new(2, 40))),
+    ];
+    let comparison =
+        SurfaceComparison::insert(r, &camera, FRAME_SIZE, width, height, filters, |_| {});
+
+    let events = (MovingBar::new(3.0, 24.0), BackgroundActivity::new(0.5))
+        .generate(0, width, height, 2000)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .collect::<Vec<_>>();
+    comparison
+        .play(
+            r,
+            &FrameScheduler::fit(60, 2000, 6.0),
+            events,
+            OutOfBoundsPolicy::Error,
+        )
+        .unwrap();
+    r.timelines_mut().sync();
 }
 
-impl Clone for TimeSurfaceCell {
-    fn clone(&self) -> Self {
-        Self {
-            start: self.start.clone(),
-            cell_size: self.cell_size.clone(),
-            y: self.y.clone(),
-            x: self.x.clone(),
-            t: self.t.clone(),
-            real_t: self.real_t.clone(),
-            _need_update: Mutex::new(self._need_update.lock().unwrap().clone()),
-            _cache: Mutex::new(self._cache.lock().unwrap().clone()),
-        }
-    }
+#[scene]
+#[output]
+fn sliding_window(r: &mut RanimScene) {
+    let _r_cam = r.insert_and_show(CameraFrame::default());
+
+    let (width, height) = (24, 24);
+    let time_surface = TimeSurface::builder(width, height)
+        .with_labels(false)
+        .with_corner_radius(0.2)
+        .build()
+        .with(|time_surface| {
+            time_surface.set_window(Some(300));
+        });
+    let r_time_surface = r.insert_and_show(time_surface);
+
+    // The disk leaves a fading trail, which keeps fading while the sensor is quiet.
+    let events = RotatingDisk::new(10.0, 4.0)
+        .with_step(5)
+        .generate(0, width, height, 2000)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .filter(|event| !(800..1200).contains(&event.t))
+        .collect::<Vec<_>>();
+    FrameScheduler::fit(60, 2000, 6.0).play_clocked(
+        r,
+        &r_time_surface,
+        events,
+        |event| event.t,
+        |time_surface, event| {
+            time_surface.accept(event).unwrap();
+        },
+        |time_surface, now| {
+            time_surface.set_now(now as usize);
+        },
+    );
+    r.timelines_mut().sync();
 }
 
-impl TimeSurfaceCell {
-    pub fn new(start: DVec3, cell_size: f64, y: usize, x: usize) -> Self {
-        Self {
-            start,
-            cell_size,
-            y,
-            x,
-            t: 0,
-            real_t: 0,
-            _need_update: Mutex::new(true),
-            _cache: Mutex::new(None),
-        }
-    }
-    pub fn set_t(&mut self, t: usize) {
-        self.t = t;
-        *self._need_update.lock().unwrap() = true;
-    }
-    pub fn accept(&mut self, real_t: usize) {
-        self.real_t = real_t;
-        *self._need_update.lock().unwrap() = true;
+#[scene]
+#[output]
+fn event_frames(r: &mut RanimScene) {
+    let _r_cam = r.insert_and_show(CameraFrame::default());
+
+    let (width, height, duration) = (16, 12, 2000);
+    let (slot_width, slot_height, y) = (4.2, 5.0, -0.4);
+    // The count image leaves room on its right for its colorbar.
+    let mut count = EventCountImage::new(width, height);
+    count
+        .fit_in(dvec3(-5.0, y, 0.0), slot_width - 0.8, slot_height)
+        .set_colormap(Colormap::Inferno);
+    let [count_min, _, count_max] = count.get_bounding_box();
+    let mut colorbar = Colorbar::new(Colormap::Inferno, 0.15, count_max.y - count_min.y);
+    colorbar.label_size = 0.15;
+    colorbar.shift(dvec3(
+        count_max.x + 0.25,
+        (count_min.y + count_max.y) / 2.0,
+        0.0,
+    ));
+    colorbar.set_linear_ticks(0.0, count.max_count() as f64, 2);
+    let mut sum = PolaritySumFrame::new(width, height);
+    sum.fit_in(dvec3(0.0, y, 0.0), slot_width, slot_height);
+    let mut voxel = VoxelGrid::new(width, height, 5, 0, duration);
+    voxel.fit_in(dvec3(4.6, y, 0.0), slot_width, slot_height + 1.0);
+    let r_count = r.insert_and_show(count);
+    let r_colorbar = r.insert_and_show(colorbar);
+    let r_sum = r.insert_and_show(sum);
+    let r_voxel = r.insert_and_show(voxel);
+    for (title, x) in [
+        ("Event count", -4.6),
+        ("Polarity sum", 0.0),
+        ("Voxel grid", 4.6),
+    ] {
+        insert_title(r, title, x);
     }
+
+    let events = (MovingBar::new(3.0, 12.0), BackgroundActivity::new(0.2))
+        .generate(0, width, height, duration)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .collect::<Vec<_>>();
+    FrameScheduler::fit(60, duration, 6.0).play_frames(
+        r,
+        events,
+        |event| event.t,
+        |r, events| {
+            let mut max_count = 0;
+            r.timeline_mut(&r_count).update_with(|count| {
+                count.feed(events, OutOfBoundsPolicy::Error).unwrap();
+                max_count = count.max_count();
+            });
+            r.timeline_mut(&r_colorbar).update_with(|colorbar| {
+                colorbar.set_linear_ticks(0.0, max_count as f64, 2);
+            });
+            r.timeline_mut(&r_sum).update_with(|sum| {
+                sum.feed(events, OutOfBoundsPolicy::Error).unwrap();
+            });
+            r.timeline_mut(&r_voxel).update_with(|voxel| {
+                voxel.feed(events, OutOfBoundsPolicy::Error).unwrap();
+            });
+        },
+    );
+    r.timelines_mut().forward(1.0);
 }
 
-// Without text: 212.1 µs
-// Without cache: 127131.2 µs
-// With cache: 110121.6 µs
-// With LRU Cache: 51117.8 µs
-// With LRU Cache and only construct world once: 2475.2 µs
-impl Extract for TimeSurfaceCell {
-    type Target = Vec<VItemPrimitive>;
-    fn extract(&self) -> Self::Target {
-        let mut _cache = self._cache.lock().unwrap();
-        let mut _need_update = self._need_update.lock().unwrap();
-        if let Some(cache) = _cache.as_ref() {
-            if !*_need_update {
-                return cache.clone();
-            }
-        }
-        let padding_ratio = 0.1;
-
-        let square_size = self.cell_size * (1.0 - padding_ratio);
-        let pos = self.start
-            + self.y as f64 * DVec3::NEG_Y * self.cell_size
-            + self.x as f64 * DVec3::X * self.cell_size;
-        let text = SvgItem::new(typst_svg(format!("{}", self.real_t).as_str())).with(|text| {
-            text.scale_to(ScaleHint::PorportionalY(square_size * 0.2))
-                .set_fill_color(manim::WHITE)
-                .put_center_on(pos);
+#[scene]
+#[output]
+fn optical_flow(r: &mut RanimScene) {
+    let _r_cam = r.insert_and_show(CameraFrame::default());
+
+    let (width, height) = (16, 16);
+    let time_surface = TimeSurface::builder(width, height)
+        .with_labels(false)
+        .build()
+        .with(|time_surface| {
+            time_surface
+                .set_flow(Some(PlaneFit::new(2, 150).with_max_residual(30.0)))
+                .set_flow_horizon(100);
         });
-        let square = Square::new(square_size).with(|square| {
-            square.put_center_on(pos);
+    let r_time_surface = r.insert_and_show(time_surface);
+
+    // The board moves right and down, and each arrow is the motion normal to the edge it sits
+    // on, which is all that a local fit can see.
+    let events = Checkerboard::new(6.0, (8.0, 4.0))
+        .with_step(5)
+        .generate(0, width, height, 1500)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .collect::<Vec<_>>();
+    FrameScheduler::fit(60, 1500, 6.0).play(
+        r,
+        &r_time_surface,
+        events,
+        |event| event.t,
+        |time_surface, event| {
+            time_surface.accept(event).unwrap();
+        },
+    );
+    r.timelines_mut().sync();
+}
+
+#[scene]
+#[output]
+fn corner_detection(r: &mut RanimScene) {
+    let _r_cam = r.insert_and_show(CameraFrame::default());
+
+    let (width, height, duration) = (32, 32, 2000);
+    let time_surface = TimeSurface::builder(width, height)
+        .with_labels(false)
+        .build()
+        .with(|time_surface| {
+            time_surface
+                .set_corner_detector(Some(Arc::new(ArcStar)))
+                .set_corner_ring_duration(150);
         });
-        let res = [VItem::from(square)]
-            .into_iter()
-            .chain(Group::<VItem>::from(text))
-            .map(|item| item.extract())
-            .collect::<Vec<_>>();
-        *_cache = Some(res.clone());
-        *_need_update = false;
-        res
-    }
+    let r_time_surface = r.insert_and_show(time_surface);
+
+    // The rings follow the four corners of the square, the edges between them stay plain.
+    let events = MovingSquare::new(10.0, (5.0, 2.5))
+        .with_start(6.0, 8.0)
+        .with_step(5)
+        .generate(0, width, height, duration)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .collect::<Vec<_>>();
+    FrameScheduler::fit(60, duration, 6.0).play_clocked(
+        r,
+        &r_time_surface,
+        events,
+        |event| event.t,
+        |time_surface, event| {
+            time_surface.accept(event).unwrap();
+        },
+        |time_surface, now| {
+            time_surface.set_now(now as usize);
+        },
+    );
+    r.timelines_mut().sync();
 }
 
-#[allow(unused)]
-#[derive(Clone)]
-struct TimeSurface {
-    width: usize,
-    height: usize,
-    cells: Vec<TimeSurfaceCell>,
+#[scene]
+#[output]
+fn video_to_events(r: &mut RanimScene) {
+    let _r_cam = r.insert_and_show(CameraFrame::default());
+
+    // Set `EVT_FRAMES` to a directory of png frames to convert them instead of the generated
+    // ones, a bright disk crossing a gradient.
+    let frames = match std::env::var_os("EVT_FRAMES") {
+        Some(dir) => read_png_frames(dir).unwrap(),
+        None => (0..24)
+            .map(|i| {
+                let (cx, cy) = (4.0 + i as f32 * 0.7, 9.0 + (i as f32 * 0.3).sin() * 3.0);
+                GrayFrame::from_fn(24, 18, |x, y| {
+                    let inside = (x as f32 + 0.5 - cx).hypot(y as f32 + 0.5 - cy) < 3.5;
+                    if inside {
+                        0.9
+                    } else {
+                        0.1 + 0.2 * x as f32 / 24.0
+                    }
+                })
+            })
+            .collect(),
+    };
+    let Some(first) = frames.first() else {
+        return;
+    };
+    let (width, height) = (first.width, first.height);
+    let frame_interval = 10_000;
+    let events = EventSimulator::new(0.2, frame_interval).simulate(&frames);
+
+    let r_frame = r.insert_and_show(FrameView::new(first.clone()).with(|view| {
+        view.fit_in(dvec3(-3.4, -0.3, 0.0), 6.4, 6.4);
+    }));
+    let r_time_surface = r.insert_and_show(
+        TimeSurface::builder(width, height)
+            .with_rect(dvec3(3.4, -0.3, 0.0), 6.4, 6.4)
+            .with_labels(false)
+            .build(),
+    );
+    for (title, x) in [("Frames", -3.4), ("Events", 3.4)] {
+        insert_title(r, title, x);
+    }
+
+    // The events of a frame are spread over the interval before it, which shows that frame.
+    let duration = (frames.len() as u64 - 1) * frame_interval;
+    FrameScheduler::fit(60, duration, 6.0).play_frames(
+        r,
+        events,
+        |event| event.t,
+        |r, events| {
+            let last_t = events.last().unwrap().t;
+            let idx = (last_t.div_ceil(frame_interval) as usize).min(frames.len() - 1);
+            r.timeline_mut(&r_frame).update_with(|view| {
+                if view.frame() != &frames[idx] {
+                    view.set_frame(frames[idx].clone());
+                }
+            });
+            r.timeline_mut(&r_time_surface).update_with(|time_surface| {
+                time_surface.feed(events, OutOfBoundsPolicy::Error).unwrap();
+            });
+        },
+    );
+    r.timelines_mut().forward(1.0);
 }
 
-impl TimeSurface {
-    pub fn new(width: usize, height: usize) -> Self {
-        let cell_size = 8.0 / height as f64;
-        let surface_width = cell_size * width as f64;
-        let start = dvec3(
-            -surface_width / 2.0 + cell_size / 2.0,
-            4.0 - cell_size / 2.0,
-            0.0,
+#[scene]
+#[output]
+fn filter_decision(r: &mut RanimScene) {
+    let r_cam = r.insert_and_show(CameraFrame::default());
+
+    let (width, height, dt) = (12, 12, 40);
+    let r_time_surface = r.insert_and_show(TimeSurface::new(width, height).with(|time_surface| {
+        time_surface.set_filter(BackgroundActivityFilter::new(dt));
+    }));
+
+    let events = (MovingBar::new(2.0, 24.0), BackgroundActivity::new(0.3))
+        .generate(0, width, height, 1000)
+        .into_iter()
+        .take(400)
+        .collect::<Vec<_>>();
+    // Pause on a signal event of the bar and later on a noise event, both off the border.
+    let inner = |labeled: &LabeledEvent| {
+        let (x, y) = (labeled.event.x as usize, labeled.event.y as usize);
+        (1..width - 1).contains(&x) && (1..height - 1).contains(&y)
+    };
+    let signal = (150..events.len()).find(|&i| events[i].signal && inner(&events[i]));
+    let noise = (250..events.len()).find(|&i| !events[i].signal && inner(&events[i]));
+    let pauses = [signal, noise].into_iter().flatten().collect::<Vec<_>>();
+
+    let scheduler = FrameScheduler::fit(60, 1000, 6.0);
+    let mut start = 0;
+    for end in pauses.into_iter().chain([events.len()]) {
+        scheduler.play(
+            r,
+            &r_time_surface,
+            events[start..end].iter().map(|labeled| labeled.event),
+            |event| event.t,
+            |time_surface, event| {
+                time_surface.accept(event).unwrap();
+            },
         );
-        Self {
-            width,
-            height,
-            cells: (0..height)
-                .cartesian_product(0..width)
-                .map(|(y, x)| TimeSurfaceCell::new(start, cell_size, y, x))
-                .collect(),
+        if let Some(labeled) = events.get(end) {
+            let time_surface = r.timeline(&r_time_surface).snapshot();
+            zoom_on_event(r, &r_cam, &time_surface, &labeled.event, 2.0).unwrap();
         }
+        start = end;
     }
-    pub fn accept(&mut self, t: usize, x: usize, y: usize) {
-        // let mut ts = Vec::with_capacity(8);
-        // if y > 0 {
-        //     ts.push(self.cells[(y - 1) * self.width + x].t);
-        //     if x > 0 {
-        //         ts.push(self.cells[(y - 1) * self.width + x - 1].t);
-        //     }
-        //     if x < self.width - 1 {
-        //         ts.push(self.cells[(y - 1) * self.width + x + 1].t);
-        //     }
-        // }
-        // if x > 0 {
-        //     ts.push(self.cells[y * self.width + x - 1].t);
-        // }
-        // if x < self.width - 1 {
-        //     ts.push(self.cells[y * self.width + x + 1].t);
-        // }
-        // if y < self.height - 1 {
-        //     ts.push(self.cells[(y + 1) * self.width + x].t);
-        //     if x > 0 {
-        //         ts.push(self.cells[(y + 1) * self.width + x - 1].t);
-        //     }
-        //     if x < self.width - 1 {
-        //         ts.push(self.cells[(y + 1) * self.width + x + 1].t);
-        //     }
-        // }
-        // self.cells[y * self.width + x].set_t(t);
-        // if ts.iter().any(|_t| _t.abs_diff(t) < 10) {
-        self.cells[y * self.width + x].accept(t);
-        // }
-    }
+    r.timelines_mut().sync();
 }
 
-impl Extract for TimeSurface {
-    type Target = Vec<VItemPrimitive>;
-    fn extract(&self) -> Self::Target {
-        let t = self.cells[0].t;
-        let (min_t, max_t) = self
-            .cells
-            .iter()
-            .map(|cell| cell.real_t)
-            .fold((t, t), |acc, x| (acc.0.min(x), acc.1.max(x)));
-        self.cells
-            .par_iter() // Without par: 207724.5 µs, With par:
-            .flat_map(|cell| {
-                let alpha = (cell.real_t - min_t) as f32 / (max_t - min_t) as f32;
-                cell.extract().with(|primitive| {
-                    primitive[0].set_fill_color(manim::BLUE_C.with_alpha(alpha * 0.7));
-                })
-            })
-            .collect()
+#[scene]
+#[output]
+fn event_history(r: &mut RanimScene) {
+    let _r_cam = r.insert_and_show(CameraFrame::default());
+
+    // The bar passes a pixel in a burst of close events, the noise is sparse and rejected.
+    let (width, height, duration) = (8, 8, 2000);
+    let styles = [
+        ("Spike train", -3.4, HistoryStyle::SpikeTrain { span: 400 }),
+        (
+            "Sparkline",
+            3.4,
+            HistoryStyle::Sparkline { max_interval: 200 },
+        ),
+    ];
+    let surfaces = styles
+        .into_iter()
+        .map(|(title, x, style)| {
+            insert_title(r, title, x);
+            r.insert_and_show(
+                TimeSurface::builder(width, height)
+                    .with_rect(dvec3(x, -0.3, 0.0), 6.4, 6.4)
+                    .with_labels(false)
+                    .with_history(8, style)
+                    .build()
+                    .with(|time_surface| {
+                        time_surface.set_filter(BackgroundActivityFilter::new(40));
+                    }),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let events = (
+        MovingBar::new(2.0, 8.0).with_step(5),
+        BackgroundActivity::new(0.05),
+    )
+        .generate(0, width, height, duration)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .collect::<Vec<_>>();
+    FrameScheduler::fit(60, duration, 6.0).play_frames(
+        r,
+        events,
+        |event| event.t,
+        |r, events| {
+            for surface in &surfaces {
+                r.timeline_mut(surface).update_with(|time_surface| {
+                    time_surface.feed(events, OutOfBoundsPolicy::Error).unwrap();
+                });
+            }
+        },
+    );
+    r.timelines_mut().forward(1.0);
+}
+
+#[scene]
+#[output]
+fn height_field(r: &mut RanimScene) {
+    let _r_cam = r.insert_and_show(CameraFrame::default());
+
+    let (width, height, duration) = (12, 12, 2000);
+    let time_surface = TimeSurface::builder(width, height)
+        .with_rect(dvec3(0.0, -0.5, 0.0), 12.0, 5.0)
+        .with_padding(0.0)
+        .build()
+        .with(|time_surface| {
+            time_surface
+                .set_normalization(Normalization::ExponentialDecay { tau: 300.0 })
+                .set_height_field(Some(4.0));
+        });
+    let r_time_surface = r.insert_and_show(time_surface);
+
+    // Few updates a second, each one played as a transform so the boxes grow and sink smoothly.
+    let events = RotatingDisk::new(5.0, 3.0)
+        .with_step(5)
+        .generate(0, width, height, duration)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .collect::<Vec<_>>();
+    let scheduler = FrameScheduler::fit(6, duration, 6.0);
+    let timeline = r.timeline_mut(&r_time_surface);
+    let start_sec = timeline.cur_sec();
+    for batch in scheduler.batches(events, |event| event.t) {
+        let end_sec = start_sec + (batch.frame + 1) as f64 * scheduler.frame_secs();
+        timeline.forward_to(end_sec - scheduler.frame_secs());
+        timeline.play_with(|time_surface| {
+            let mut next = time_surface.clone();
+            next.feed(&batch.events, OutOfBoundsPolicy::Error).unwrap();
+            time_surface
+                .transform_to(next)
+                .with_duration(scheduler.frame_secs())
+        });
     }
+    r.timelines_mut().sync();
+    r.timelines_mut().forward(1.0);
+}
+
+#[scene]
+#[output]
+fn event_rain(r: &mut RanimScene) {
+    let _r_cam = r.insert_and_show(CameraFrame::default());
+
+    let (width, height, duration) = (64, 48, 3000);
+    let r_scatter = r.insert_and_show(EventScatter::new(width, height, 300).with(|scatter| {
+        scatter.fit_in(DVec3::ZERO, 13.0, 7.5);
+    }));
+
+    // Every edge of the board leaves a trail of drops behind it.
+    let events = (
+        Checkerboard::new(8.0, (12.0, 6.0)).with_step(2),
+        BackgroundActivity::new(0.05),
+    )
+        .generate(0, width, height, duration)
+        .into_iter()
+        .map(|labeled| labeled.event)
+        .collect::<Vec<_>>();
+    FrameScheduler::fit(60, duration, 6.0).play_clocked(
+        r,
+        &r_scatter,
+        events,
+        |event| event.t,
+        |scatter, event| {
+            scatter.accept(event).unwrap();
+        },
+        |scatter, now| {
+            scatter.set_now(now);
+        },
+    );
+    r.timelines_mut().sync();
 }
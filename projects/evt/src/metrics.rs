@@ -0,0 +1,232 @@
+use ranim::{
+    color::palettes::manim,
+    glam::{DVec3, dvec3},
+    items::vitem::{VItem, geometry::Rectangle},
+    prelude::*,
+    render::primitives::{Extract, vitem::VItemPrimitive},
+};
+
+use crate::glyph::glyph_label;
+
+/// The confusion matrix of a denoising filter, signal is the positive class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DenoiseMetrics {
+    /// Signal accepted
+    pub true_positive: usize,
+    /// Noise accepted
+    pub false_positive: usize,
+    /// Signal rejected
+    pub false_negative: usize,
+    /// Noise rejected
+    pub true_negative: usize,
+}
+
+impl DenoiseMetrics {
+    pub fn record(&mut self, accepted: bool, signal: bool) {
+        match (accepted, signal) {
+            (true, true) => self.true_positive += 1,
+            (true, false) => self.false_positive += 1,
+            (false, true) => self.false_negative += 1,
+            (false, false) => self.true_negative += 1,
+        }
+    }
+    pub fn total(&self) -> usize {
+        self.true_positive + self.false_positive + self.false_negative + self.true_negative
+    }
+    /// The fraction of the accepted events that are signal, `None` if nothing is accepted.
+    pub fn precision(&self) -> Option<f64> {
+        let accepted = self.true_positive + self.false_positive;
+        (accepted > 0).then(|| self.true_positive as f64 / accepted as f64)
+    }
+    /// The fraction of the signal that is accepted, `None` if there is no signal.
+    pub fn recall(&self) -> Option<f64> {
+        let signal = self.true_positive + self.false_negative;
+        (signal > 0).then(|| self.true_positive as f64 / signal as f64)
+    }
+    /// The harmonic mean of precision and recall, `None` if either is undefined.
+    pub fn f1(&self) -> Option<f64> {
+        let (precision, recall) = (self.precision()?, self.recall()?);
+        Some(if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        })
+    }
+}
+
+/// A bar chart of the precision, recall and F1 of a [`DenoiseMetrics`].
+#[derive(Clone)]
+pub struct MetricsReadout {
+    pub metrics: DenoiseMetrics,
+    pub center: DVec3,
+    /// The width of the whole readout, the bars take the middle part
+    pub width: f64,
+    pub bar_height: f64,
+}
+
+impl MetricsReadout {
+    pub fn new(width: f64, bar_height: f64) -> Self {
+        Self {
+            metrics: DenoiseMetrics::default(),
+            center: DVec3::ZERO,
+            width,
+            bar_height,
+        }
+    }
+    pub fn set_metrics(&mut self, metrics: DenoiseMetrics) -> &mut Self {
+        self.metrics = metrics;
+        self
+    }
+    fn rows(&self) -> [(&'static str, Option<f64>, color::AlphaColor<color::Srgb>); 3] {
+        [
+            ("P", self.metrics.precision(), manim::BLUE_C),
+            ("R", self.metrics.recall(), manim::GREEN_C),
+            ("F1", self.metrics.f1(), manim::YELLOW_C),
+        ]
+    }
+    fn height(&self) -> f64 {
+        // Half a bar of gap between the rows.
+        self.bar_height * (3.0 * 1.5 - 0.5)
+    }
+}
+
+impl BoundingBox for MetricsReadout {
+    fn get_bounding_box(&self) -> [DVec3; 3] {
+        let half = dvec3(self.width, self.height(), 0.0) / 2.0;
+        [self.center - half, self.center, self.center + half]
+    }
+}
+
+impl Shift for MetricsReadout {
+    fn shift(&mut self, shift: DVec3) -> &mut Self {
+        self.center += shift;
+        self
+    }
+}
+
+impl Extract for MetricsReadout {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let label_width = self.width * 0.2;
+        let bar_width = self.width * 0.55;
+        let left = self.center.x - self.width / 2.0;
+        let bar_left = left + label_width;
+        let label_size = self.bar_height * 0.6;
+        let top = self.center.y + self.height() / 2.0 - self.bar_height / 2.0;
+
+        let label = |text: &str, x: f64, y: f64| {
+            glyph_label(text, label_size, dvec3(x, y, self.center.z)).with(|label| {
+                label.set_fill_color(manim::WHITE);
+            })
+        };
+
+        let mut items = vec![];
+        for (i, (name, value, color)) in self.rows().into_iter().enumerate() {
+            let y = top - i as f64 * self.bar_height * 1.5;
+            items.extend(label(name, left + label_width / 2.0, y));
+            let fill = value.unwrap_or(0.0).clamp(0.0, 1.0);
+            if fill > 0.0 {
+                items.push(VItem::from(
+                    Rectangle::new(bar_width * fill, self.bar_height).with(|bar| {
+                        bar.set_fill_color(color.with_alpha(0.8))
+                            .set_stroke_color(color.with_alpha(0.0))
+                            .put_center_on(dvec3(
+                                bar_left + bar_width * fill / 2.0,
+                                y,
+                                self.center.z,
+                            ));
+                    }),
+                ));
+            }
+            items.push(VItem::from(
+                Rectangle::new(bar_width, self.bar_height).with(|outline| {
+                    outline.set_stroke_color(color).put_center_on(dvec3(
+                        bar_left + bar_width / 2.0,
+                        y,
+                        self.center.z,
+                    ));
+                }),
+            ));
+            let text = value.map_or("-".to_string(), |value| format!("{value:.2}"));
+            let value_x = bar_left + bar_width + (self.width - label_width - bar_width) / 2.0;
+            items.extend(label(&text, value_x, y));
+        }
+        items.into_iter().map(|item| item.extract()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(
+        true_positive: usize,
+        false_positive: usize,
+        false_negative: usize,
+        true_negative: usize,
+    ) -> DenoiseMetrics {
+        DenoiseMetrics {
+            true_positive,
+            false_positive,
+            false_negative,
+            true_negative,
+        }
+    }
+
+    fn assert_close(value: Option<f64>, expected: f64) {
+        let value = value.expect("a value");
+        assert!((value - expected).abs() < 1e-12, "{value} != {expected}");
+    }
+
+    #[test]
+    fn records_the_confusion_matrix() {
+        let mut recorded = DenoiseMetrics::default();
+        for (accepted, signal) in [(true, true), (true, true), (true, false), (false, true)] {
+            recorded.record(accepted, signal);
+        }
+        recorded.record(false, false);
+        assert_eq!(recorded, metrics(2, 1, 1, 1));
+        assert_eq!(recorded.total(), 5);
+    }
+
+    #[test]
+    fn precision_recall_and_f1() {
+        let m = metrics(6, 2, 3, 9);
+        assert_close(m.precision(), 0.75);
+        assert_close(m.recall(), 6.0 / 9.0);
+        assert_close(m.f1(), 2.0 * 0.75 * (6.0 / 9.0) / (0.75 + 6.0 / 9.0));
+
+        let perfect = metrics(4, 0, 0, 7);
+        assert_close(perfect.precision(), 1.0);
+        assert_close(perfect.recall(), 1.0);
+        assert_close(perfect.f1(), 1.0);
+    }
+
+    #[test]
+    fn undefined_without_accepted_events_or_signal() {
+        // Nothing accepted, the recall is 0.0 but the precision is undefined.
+        let m = metrics(0, 0, 5, 3);
+        assert_eq!(m.precision(), None);
+        assert_close(m.recall(), 0.0);
+        assert_eq!(m.f1(), None);
+        // No signal, the precision is 0.0 but the recall is undefined.
+        let m = metrics(0, 4, 0, 3);
+        assert_close(m.precision(), 0.0);
+        assert_eq!(m.recall(), None);
+        assert_eq!(m.f1(), None);
+
+        let empty = DenoiseMetrics::default();
+        assert_eq!(
+            (empty.precision(), empty.recall(), empty.f1()),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn f1_is_zero_without_true_positives() {
+        let m = metrics(0, 4, 5, 3);
+        assert_close(m.precision(), 0.0);
+        assert_close(m.recall(), 0.0);
+        assert_close(m.f1(), 0.0);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::event::Polarity;
+
+/// How the timestamps of a time surface are mapped into [0.0, 1.0] for coloring.
+///
+/// Pixels without any event are always mapped to 0.0, and degenerate ranges never produce NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    /// Linear from the oldest timestamp to the newest one.
+    ///
+    /// With a `window`, the range is `now - window..=now` instead, older timestamps are clamped to 0.0.
+    Linear { window: Option<usize> },
+    /// `exp(-(now - t) / tau)`, the classic SAE time surface.
+    ExponentialDecay { tau: f64 },
+    /// The rank of the timestamp among the distinct timestamps, equal timestamps share a rank.
+    Rank,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization::Linear { window: None }
+    }
+}
+
+/// The sorted and deduplicated timestamps that the range and the ranks are taken from.
+pub fn reference_times(ts: &[Option<usize>]) -> Vec<usize> {
+    let mut reference = ts.iter().flatten().copied().collect::<Vec<_>>();
+    reference.sort_unstable();
+    reference.dedup();
+    reference
+}
+
+impl Normalization {
+    /// Normalize the timestamps, `now` is the time of the latest event.
+    pub fn normalize(&self, ts: &[Option<usize>], now: usize) -> Vec<f32> {
+        self.normalize_with(ts, now, &reference_times(ts))
+    }
+    /// Normalize the timestamps against the range and the ranks of `reference`, which is
+    /// sorted and deduplicated, see [`reference_times`].
+    ///
+    /// Normalizing several surfaces against the same reference makes their colors comparable.
+    pub fn normalize_with(
+        &self,
+        ts: &[Option<usize>],
+        now: usize,
+        reference: &[usize],
+    ) -> Vec<f32> {
+        match *self {
+            Normalization::Linear { window } => {
+                let (min_t, max_t) = match window {
+                    Some(window) => (now.saturating_sub(window), now),
+                    None => (
+                        reference.first().copied().unwrap_or(usize::MAX),
+                        reference.last().copied().unwrap_or(0),
+                    ),
+                };
+                ts.iter()
+                    .map(|t| match *t {
+                        None => 0.0,
+                        // Everything is at the same time, which is the newest.
+                        Some(t) if max_t <= min_t => (t >= max_t) as u8 as f32,
+                        Some(t) => (t.saturating_sub(min_t) as f64 / (max_t - min_t) as f64)
+                            .clamp(0.0, 1.0) as f32,
+                    })
+                    .collect()
+            }
+            Normalization::ExponentialDecay { tau } => ts
+                .iter()
+                .map(|t| match *t {
+                    None => 0.0,
+                    Some(t) if tau <= 0.0 => (t >= now) as u8 as f32,
+                    Some(t) => (-(now.saturating_sub(t) as f64) / tau).exp() as f32,
+                })
+                .collect(),
+            Normalization::Rank => {
+                let max_rank = reference.len().saturating_sub(1);
+                ts.iter()
+                    .map(|t| match *t {
+                        None => 0.0,
+                        Some(_) if max_rank == 0 => 1.0,
+                        Some(t) => {
+                            // Timestamps missing from the reference take the rank of the
+                            // closest older one.
+                            let rank = match reference.binary_search(&t) {
+                                Ok(rank) => rank,
+                                Err(pos) => pos.saturating_sub(1),
+                            };
+                            rank as f32 / max_rank as f32
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The reference times of each channel, shared between several surfaces so that they are
+/// normalized against the same range.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SharedReference {
+    pub merged: Vec<usize>,
+    pub on: Vec<usize>,
+    pub off: Vec<usize>,
+}
+
+impl SharedReference {
+    /// `None` is the merged channel.
+    pub fn get(&self, channel: Option<Polarity>) -> &[usize] {
+        match channel {
+            None => &self.merged,
+            Some(Polarity::On) => &self.on,
+            Some(Polarity::Off) => &self.off,
+        }
+    }
+    /// Merge sorted and deduplicated times into the channel.
+    pub fn merge(&mut self, channel: Option<Polarity>, times: &[usize]) {
+        let reference = match channel {
+            None => &mut self.merged,
+            Some(Polarity::On) => &mut self.on,
+            Some(Polarity::Off) => &mut self.off,
+        };
+        reference.extend_from_slice(times);
+        reference.sort_unstable();
+        reference.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Normalization; 5] = [
+        Normalization::Linear { window: None },
+        Normalization::Linear { window: Some(10) },
+        Normalization::ExponentialDecay { tau: 10.0 },
+        Normalization::ExponentialDecay { tau: 0.0 },
+        Normalization::Rank,
+    ];
+
+    fn assert_close(values: &[f32], expected: &[f32]) {
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-6,
+                "{values:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn linear() {
+        let ts = [Some(10), None, Some(15), Some(20)];
+        let linear = Normalization::Linear { window: None };
+        assert_close(&linear.normalize(&ts, 20), &[0.0, 0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn linear_window() {
+        let ts = [Some(10), None, Some(15), Some(20)];
+        let linear = Normalization::Linear { window: Some(4) };
+        assert_close(&linear.normalize(&ts, 20), &[0.0, 0.0, 0.0, 1.0]);
+        let linear = Normalization::Linear { window: Some(10) };
+        assert_close(&linear.normalize(&ts, 20), &[0.0, 0.0, 0.5, 1.0]);
+        // The window ends at `now`, not at the newest timestamp.
+        assert_close(&linear.normalize(&ts, 25), &[0.0, 0.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn exponential_decay() {
+        let ts = [Some(10), None, Some(20)];
+        let decay = Normalization::ExponentialDecay { tau: 10.0 };
+        assert_close(&decay.normalize(&ts, 20), &[(-1.0f32).exp(), 0.0, 1.0]);
+    }
+
+    #[test]
+    fn exponential_decay_without_tau() {
+        let ts = [Some(10), None, Some(20)];
+        for tau in [0.0, -1.0] {
+            let decay = Normalization::ExponentialDecay { tau };
+            assert_close(&decay.normalize(&ts, 20), &[0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn rank() {
+        let ts = [Some(100), None, Some(5), Some(100), Some(7)];
+        assert_close(
+            &Normalization::Rank.normalize(&ts, 100),
+            &[1.0, 0.0, 0.0, 1.0, 0.5],
+        );
+        // A timestamp missing from the reference takes the rank of the closest older one.
+        assert_close(
+            &Normalization::Rank.normalize_with(&[Some(6)], 100, &[5, 7, 100]),
+            &[0.0],
+        );
+    }
+
+    #[test]
+    fn no_events() {
+        for normalization in ALL {
+            assert_close(&normalization.normalize(&[None, None], 0), &[0.0, 0.0]);
+            assert!(normalization.normalize(&[], 0).is_empty());
+        }
+    }
+
+    #[test]
+    fn single_timestamp() {
+        // Every event at the same time, so the newest is also the oldest.
+        for normalization in ALL {
+            let values = normalization.normalize(&[Some(7), None, Some(7)], 7);
+            assert_close(&values, &[1.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn empty_range_has_no_nan() {
+        let linear = Normalization::Linear { window: Some(0) };
+        assert_close(&linear.normalize(&[Some(3), Some(5)], 5), &[0.0, 1.0]);
+        let linear = Normalization::Linear { window: None };
+        assert_close(&linear.normalize_with(&[Some(3)], 3, &[]), &[1.0]);
+        for normalization in ALL {
+            let values = normalization.normalize(&[Some(0), Some(usize::MAX)], usize::MAX);
+            assert!(values.iter().all(|value| value.is_finite()), "{values:?}");
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::{ops::Index, sync::Arc};
+
+use rayon::prelude::*;
+
+/// A persistent vector with structural sharing.
+///
+/// The elements are stored in `Arc`ed chunks of `Arc`ed elements, cloning it is O(1), and
+/// [`PersistentVec::get_mut`] only copies the chunk and the element it touches when they are
+/// shared with other clones, so keeping a snapshot after each update costs O(sqrt(n)) instead
+/// of O(n).
+pub struct PersistentVec<T> {
+    chunks: Arc<Vec<Arc<Vec<Arc<T>>>>>,
+    chunk_size: usize,
+    len: usize,
+}
+
+impl<T> Clone for PersistentVec<T> {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+            chunk_size: self.chunk_size,
+            len: self.len,
+        }
+    }
+}
+
+impl<T> FromIterator<T> for PersistentVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let items = iter.into_iter().map(Arc::new).collect::<Vec<_>>();
+        let len = items.len();
+        // sqrt(n) balances the cost of copying the chunk list and copying a chunk.
+        let chunk_size = ((len as f64).sqrt().ceil() as usize).max(1);
+        let mut items = items.into_iter();
+        let chunks = (0..len.div_ceil(chunk_size))
+            .map(|_| Arc::new(items.by_ref().take(chunk_size).collect()))
+            .collect();
+        Self {
+            chunks: Arc::new(chunks),
+            chunk_size,
+            len,
+        }
+    }
+}
+
+impl<T> PersistentVec<T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        (idx < self.len).then(|| self.chunks[idx / self.chunk_size][idx % self.chunk_size].as_ref())
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter().map(|item| item.as_ref()))
+    }
+}
+
+impl<T: Sync + Send> PersistentVec<T> {
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = &T> {
+        (0..self.len).into_par_iter().map(|idx| &self[idx])
+    }
+}
+
+impl<T: Clone> PersistentVec<T> {
+    /// Get the mutable reference of an element, copying it and its chunk if they are shared.
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        if idx >= self.len {
+            return None;
+        }
+        let chunk = Arc::make_mut(&mut Arc::make_mut(&mut self.chunks)[idx / self.chunk_size]);
+        Some(Arc::make_mut(&mut chunk[idx % self.chunk_size]))
+    }
+}
+
+impl<T> Index<usize> for PersistentVec<T> {
+    type Output = T;
+    fn index(&self, idx: usize) -> &Self::Output {
+        self.get(idx).expect("index out of bounds")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_mut_on_a_clone_leaves_the_original() {
+        let original = (0..10).collect::<PersistentVec<_>>();
+        let mut clone = original.clone();
+        *clone.get_mut(4).unwrap() = 40;
+        assert_eq!(original[4], 4);
+        assert_eq!(clone[4], 40);
+        assert_eq!(
+            original.iter().copied().collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        assert_eq!(clone.get_mut(10), None);
+    }
+
+    #[test]
+    fn untouched_chunks_stay_shared() {
+        // 16 elements in 4 chunks of 4.
+        let original = (0..16).collect::<PersistentVec<_>>();
+        assert_eq!((original.chunk_size, original.chunks.len()), (4, 4));
+        let mut clone = original.clone();
+        assert!(Arc::ptr_eq(&original.chunks, &clone.chunks));
+
+        *clone.get_mut(5).unwrap() = 50;
+        assert!(!Arc::ptr_eq(&original.chunks, &clone.chunks));
+        for (idx, (a, b)) in original.chunks.iter().zip(clone.chunks.iter()).enumerate() {
+            assert_eq!(Arc::ptr_eq(a, b), idx != 1, "chunk {idx}");
+        }
+        // In the copied chunk, only the written element is copied.
+        for (idx, (a, b)) in original.chunks[1]
+            .iter()
+            .zip(clone.chunks[1].iter())
+            .enumerate()
+        {
+            assert_eq!(Arc::ptr_eq(a, b), idx != 1, "element {idx}");
+        }
+
+        // A second write to the same chunk doesn't copy it again.
+        let chunk = Arc::as_ptr(&clone.chunks[1]);
+        *clone.get_mut(6).unwrap() = 60;
+        assert_eq!(Arc::as_ptr(&clone.chunks[1]), chunk);
+    }
+
+    #[test]
+    fn indexes_across_chunk_boundaries() {
+        // 10 elements in chunks of 4, the last one is partial.
+        let vec = (0..10).map(|i| i * 10).collect::<PersistentVec<_>>();
+        assert_eq!(vec.len(), 10);
+        assert!(!vec.is_empty());
+        for idx in [0, 3, 4, 7, 8, 9] {
+            assert_eq!(vec.get(idx), Some(&(idx * 10)));
+            assert_eq!(vec[idx], idx * 10);
+        }
+        assert_eq!(vec.get(10), None);
+        assert_eq!(
+            vec.par_iter().copied().collect::<Vec<_>>(),
+            vec.iter().copied().collect::<Vec<_>>()
+        );
+
+        let empty = std::iter::empty::<usize>().collect::<PersistentVec<_>>();
+        assert_eq!(empty.len(), 0);
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(0), None);
+        assert_eq!(empty.iter().count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn index_out_of_bounds_panics() {
+        let vec = (0..10).collect::<PersistentVec<_>>();
+        let _ = vec[10];
+    }
+}
@@ -0,0 +1,533 @@
+//! Readers for event-camera recordings.
+//!
+//! Supported formats:
+//! - Prophesee EVT 2.0 / EVT 3.0 `.raw` (the `%`-prefixed ascii header selects the version)
+//! - iniVation AEDAT 4 `.aedat4`, uncompressed or LZ4 / Zstd compressed
+//! - plain text `.csv` / `.txt` with `t,x,y,p` columns, `t` in microseconds
+use std::{
+    borrow::Cow,
+    fmt, fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::event::{Event, Polarity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFormat {
+    Evt2,
+    Evt3,
+    Aedat4,
+    Csv,
+}
+
+impl fmt::Display for EventFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EventFormat::Evt2 => "EVT 2.0",
+            EventFormat::Evt3 => "EVT 3.0",
+            EventFormat::Aedat4 => "AEDAT 4",
+            EventFormat::Csv => "CSV",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    UnknownFormat,
+    Malformed { format: EventFormat, reason: String },
+    Unsupported { format: EventFormat, reason: String },
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(err) => write!(f, "io error: {err}"),
+            ReadError::UnknownFormat => write!(f, "unknown event file format"),
+            ReadError::Malformed { format, reason } => {
+                write!(f, "malformed {format} data: {reason}")
+            }
+            ReadError::Unsupported { format, reason } => {
+                write!(f, "unsupported {format} data: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        ReadError::Io(err)
+    }
+}
+
+fn malformed(format: EventFormat, reason: impl Into<String>) -> ReadError {
+    ReadError::Malformed {
+        format,
+        reason: reason.into(),
+    }
+}
+
+/// Read all events in the file, the format is detected from the content and the extension.
+///
+/// The result is sorted by `t`, keeping the file order for equal timestamps.
+pub fn read_events(path: impl AsRef<Path>) -> Result<Vec<Event>, ReadError> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    let format = detect_format(&data)
+        .or_else(|| match path.extension()?.to_str()? {
+            "csv" | "txt" => Some(EventFormat::Csv),
+            _ => None,
+        })
+        .ok_or(ReadError::UnknownFormat)?;
+    let mut events = parse_events(&data, format)?;
+    events.sort_by_key(|event| event.t);
+    Ok(events)
+}
+
+/// Detect the format from the leading bytes, returns `None` for headerless data such as csv.
+pub fn detect_format(data: &[u8]) -> Option<EventFormat> {
+    if data.starts_with(AEDAT4_MAGIC) {
+        return Some(EventFormat::Aedat4);
+    }
+    if data.first() == Some(&b'%') {
+        let (header, _) = split_raw_header(data);
+        return Some(header.format);
+    }
+    None
+}
+
+pub fn parse_events(data: &[u8], format: EventFormat) -> Result<Vec<Event>, ReadError> {
+    match format {
+        EventFormat::Evt2 => Ok(parse_evt2(split_raw_header(data).1)),
+        EventFormat::Evt3 => Ok(parse_evt3(split_raw_header(data).1)),
+        EventFormat::Aedat4 => parse_aedat4(data),
+        EventFormat::Csv => {
+            let text = std::str::from_utf8(data)
+                .map_err(|err| malformed(EventFormat::Csv, err.to_string()))?;
+            parse_csv(text)
+        }
+    }
+}
+
+// MARK: Prophesee raw
+
+struct RawHeader {
+    format: EventFormat,
+}
+
+/// Split the `%`-prefixed ascii header of a `.raw` file from its binary payload.
+///
+/// The header is either terminated by a `% end` line or by the first line without `%`.
+fn split_raw_header(data: &[u8]) -> (RawHeader, &[u8]) {
+    // Metavision defaults to EVT 2.0 when the header does not say otherwise.
+    let mut header = RawHeader {
+        format: EventFormat::Evt2,
+    };
+    let mut pos = 0;
+    while data.get(pos) == Some(&b'%') {
+        let end = data[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|i| pos + i)
+            .unwrap_or(data.len());
+        let line = String::from_utf8_lossy(&data[pos..end]);
+        let line = line.trim_start_matches('%').trim();
+        pos = (end + 1).min(data.len());
+
+        if line == "end" {
+            break;
+        }
+        if let Some(version) = line.strip_prefix("evt ") {
+            if version.trim().starts_with('3') {
+                header.format = EventFormat::Evt3;
+            }
+        } else if let Some(format) = line.strip_prefix("format ") {
+            if format.starts_with("EVT3") {
+                header.format = EventFormat::Evt3;
+            } else if format.starts_with("EVT2") {
+                header.format = EventFormat::Evt2;
+            }
+        }
+    }
+    (header, &data[pos..])
+}
+
+/// Parse the binary payload of an EVT 2.0 stream.
+///
+/// Each 32-bit little endian word has its type in the top 4 bits, CD events carry the
+/// low 6 bits of the timestamp while `EVT_TIME_HIGH` carries the upper 28 bits.
+pub fn parse_evt2(payload: &[u8]) -> Vec<Event> {
+    let mut events = Vec::with_capacity(payload.len() / 4);
+    let mut time_high = 0u64;
+    for word in payload.chunks_exact(4) {
+        let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        match word >> 28 {
+            // CD_OFF / CD_ON
+            ty @ (0x0 | 0x1) => {
+                let t = (time_high << 6) | ((word >> 22) & 0x3f) as u64;
+                let x = ((word >> 11) & 0x7ff) as u16;
+                let y = (word & 0x7ff) as u16;
+                events.push(Event::new(t, x, y, Polarity::from(ty == 0x1)));
+            }
+            // EVT_TIME_HIGH
+            0x8 => time_high = (word & 0x0fff_ffff) as u64,
+            // EXT_TRIGGER, OTHERS, CONTINUED
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Parse the binary payload of an EVT 3.0 stream.
+///
+/// EVT 3.0 is a stateful 16-bit word stream: `y`, the time and the vector base `x` are
+/// remembered between words, and `VECT_12` / `VECT_8` masks expand into several events.
+/// The events that a vector puts past the 11-bit range of `x` are dropped.
+pub fn parse_evt3(payload: &[u8]) -> Vec<Event> {
+    let mut events = Vec::with_capacity(payload.len() / 2);
+    // In units of 4096us, including the 24-bit wrap arounds.
+    let mut time_high = 0u64;
+    let mut time_low = 0u64;
+    let mut y = 0u16;
+    let mut base_x = 0u16;
+    let mut polarity = Polarity::Off;
+
+    for word in payload.chunks_exact(2) {
+        let word = u16::from_le_bytes([word[0], word[1]]);
+        let t = (time_high << 12) | time_low;
+        match word >> 12 {
+            // EVT_ADDR_Y
+            0x0 => y = word & 0x7ff,
+            // EVT_ADDR_X
+            0x2 => events.push(Event::new(
+                t,
+                word & 0x7ff,
+                y,
+                Polarity::from(word & 0x800 != 0),
+            )),
+            // VECT_BASE_X
+            0x3 => {
+                base_x = word & 0x7ff;
+                polarity = Polarity::from(word & 0x800 != 0);
+            }
+            // VECT_12 / VECT_8
+            ty @ (0x4 | 0x5) => {
+                let len = if ty == 0x4 { 12 } else { 8 };
+                events.extend(
+                    (0..len)
+                        .filter(|i| word & (1 << i) != 0)
+                        .filter_map(|i| base_x.checked_add(i).filter(|&x| x <= 0x7ff))
+                        .map(|x| Event::new(t, x, y, polarity)),
+                );
+                base_x = base_x.saturating_add(len);
+            }
+            // EVT_TIME_LOW
+            0x6 => time_low = (word & 0xfff) as u64,
+            // EVT_TIME_HIGH
+            0x8 => {
+                let high = (word & 0xfff) as u64;
+                let prev = time_high & 0xfff;
+                if high < prev && prev - high > 0x800 {
+                    time_high += 0x1000;
+                }
+                time_high = (time_high & !0xfff) | high;
+            }
+            // EXT_TRIGGER, OTHERS, CONTINUED_4, CONTINUED_12
+            _ => {}
+        }
+    }
+    events
+}
+
+// MARK: AEDAT 4
+
+const AEDAT4_MAGIC: &[u8] = b"#!AER-DAT4.0\r\n";
+
+fn out_of_range() -> ReadError {
+    malformed(EventFormat::Aedat4, "flatbuffer offset out of range")
+}
+
+/// The `CompressionType` of the file header, every packet is compressed on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl Compression {
+    fn from_header(compression: i32) -> Option<Self> {
+        match compression {
+            0 => Some(Compression::None),
+            // LZ4 / LZ4_HIGH
+            1 | 2 => Some(Compression::Lz4),
+            // ZSTD / ZSTD_HIGH
+            3 | 4 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+    /// Decompress a packet, which is a single LZ4 or Zstd frame.
+    fn decompress(self, packet: &[u8]) -> Result<Cow<'_, [u8]>, ReadError> {
+        let mut decompressed = vec![];
+        let result = match self {
+            Compression::None => return Ok(Cow::Borrowed(packet)),
+            Compression::Lz4 => {
+                lz4_flex::frame::FrameDecoder::new(packet).read_to_end(&mut decompressed)
+            }
+            Compression::Zstd => zstd::stream::Decoder::new(packet)
+                .and_then(|mut decoder| decoder.read_to_end(&mut decompressed)),
+        };
+        result.map_err(|err| malformed(EventFormat::Aedat4, format!("{self:?} packet: {err}")))?;
+        Ok(Cow::Owned(decompressed))
+    }
+}
+
+/// Minimal bounds checked accessors over a flatbuffer.
+struct FlatBuffer<'a>(&'a [u8]);
+
+impl<'a> FlatBuffer<'a> {
+    fn bytes<const N: usize>(&self, pos: usize) -> Result<[u8; N], ReadError> {
+        pos.checked_add(N)
+            .and_then(|end| self.0.get(pos..end))
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(out_of_range)
+    }
+    fn u16(&self, pos: usize) -> Result<u16, ReadError> {
+        self.bytes(pos).map(u16::from_le_bytes)
+    }
+    fn i16(&self, pos: usize) -> Result<i16, ReadError> {
+        self.bytes(pos).map(i16::from_le_bytes)
+    }
+    fn u32(&self, pos: usize) -> Result<u32, ReadError> {
+        self.bytes(pos).map(u32::from_le_bytes)
+    }
+    fn i32(&self, pos: usize) -> Result<i32, ReadError> {
+        self.bytes(pos).map(i32::from_le_bytes)
+    }
+    fn i64(&self, pos: usize) -> Result<i64, ReadError> {
+        self.bytes(pos).map(i64::from_le_bytes)
+    }
+    fn root_table(&self) -> Result<usize, ReadError> {
+        self.u32(0).map(|offset| offset as usize)
+    }
+    /// The absolute position of the `idx`-th field of the table, `None` if it is absent.
+    fn field(&self, table: usize, idx: usize) -> Result<Option<usize>, ReadError> {
+        let vtable = (table as i64)
+            .checked_sub(self.i32(table)? as i64)
+            .and_then(|vtable| usize::try_from(vtable).ok())
+            .ok_or_else(out_of_range)?;
+        let vtable_len = self.u16(vtable)? as usize;
+        let entry = 4 + 2 * idx;
+        if entry >= vtable_len {
+            return Ok(None);
+        }
+        let offset = self.u16(vtable + entry)? as usize;
+        if offset == 0 {
+            return Ok(None);
+        }
+        table.checked_add(offset).map(Some).ok_or_else(out_of_range)
+    }
+}
+
+/// Parse an AEDAT 4 file, only the `EVTS` packets are read.
+///
+/// Events with negative coordinates or timestamps are [`ReadError::Malformed`].
+pub fn parse_aedat4(data: &[u8]) -> Result<Vec<Event>, ReadError> {
+    // An `EventPacket` element is `struct Event { timestamp: i64; x: i16; y: i16; on: bool; }`,
+    // which is padded to 16 bytes.
+    const EVENT_SIZE: usize = 16;
+
+    let data = data
+        .strip_prefix(AEDAT4_MAGIC)
+        .ok_or_else(|| malformed(EventFormat::Aedat4, "missing `#!AER-DAT4.0` magic"))?;
+    let file = FlatBuffer(data);
+    let header_len = file.i32(0)?;
+    let header_len = usize::try_from(header_len)
+        .map_err(|_| malformed(EventFormat::Aedat4, "negative header size"))?;
+    let header = FlatBuffer(
+        data.get(4..4 + header_len)
+            .ok_or_else(|| malformed(EventFormat::Aedat4, "truncated header"))?,
+    );
+
+    let header_table = header.root_table()?;
+    let compression = match header.field(header_table, 0)? {
+        Some(pos) => header.i32(pos)?,
+        None => 0,
+    };
+    let compression =
+        Compression::from_header(compression).ok_or_else(|| ReadError::Unsupported {
+            format: EventFormat::Aedat4,
+            reason: format!("compression type {compression}"),
+        })?;
+    // The data table is appended after the last packet, positions are relative to the file start.
+    let data_end = match header.field(header_table, 1)? {
+        Some(pos) => match header.i64(pos)? {
+            pos if pos >= 0 => (pos as usize).saturating_sub(AEDAT4_MAGIC.len()),
+            _ => data.len(),
+        },
+        None => data.len(),
+    }
+    .min(data.len());
+
+    let mut events = vec![];
+    let mut pos = 4 + header_len;
+    while pos + 8 <= data_end {
+        let packet_len = usize::try_from(file.i32(pos + 4)?)
+            .map_err(|_| malformed(EventFormat::Aedat4, "negative packet size"))?;
+        let packet = data
+            .get(pos + 8..pos + 8 + packet_len)
+            .ok_or_else(|| malformed(EventFormat::Aedat4, "truncated packet"))?;
+        pos += 8 + packet_len;
+        let packet = compression.decompress(packet)?;
+
+        // The packets are size prefixed flatbuffers, the identifier follows the root offset.
+        let Some(packet) = packet
+            .get(4..)
+            .filter(|packet| packet.get(4..8) == Some(b"EVTS"))
+        else {
+            continue;
+        };
+        let packet = FlatBuffer(packet);
+        let table = packet.root_table()?;
+        let Some(elements) = packet.field(table, 0)? else {
+            continue;
+        };
+        let vector = elements
+            .checked_add(packet.u32(elements)? as usize)
+            .ok_or_else(out_of_range)?;
+        let len = packet.u32(vector)? as usize;
+        // The length is read from the file, check it against the packet before allocating.
+        if len
+            .checked_mul(EVENT_SIZE)
+            .and_then(|size| size.checked_add(vector + 4))
+            .is_none_or(|end| end > packet.0.len())
+        {
+            return Err(malformed(
+                EventFormat::Aedat4,
+                format!("{len} events do not fit in the packet"),
+            ));
+        }
+        events.reserve(len);
+        for i in 0..len {
+            let base = vector + 4 + i * EVENT_SIZE;
+            let [on] = packet.bytes::<1>(base + 12)?;
+            let t = packet.i64(base)?;
+            let (x, y) = (packet.i16(base + 8)?, packet.i16(base + 10)?);
+            let (Ok(x), Ok(y)) = (u16::try_from(x), u16::try_from(y)) else {
+                return Err(malformed(
+                    EventFormat::Aedat4,
+                    format!("negative event coordinates ({x}, {y})"),
+                ));
+            };
+            let Ok(t) = u64::try_from(t) else {
+                return Err(malformed(
+                    EventFormat::Aedat4,
+                    format!("negative event timestamp {t}"),
+                ));
+            };
+            events.push(Event::new(t, x, y, Polarity::from(on != 0)));
+        }
+    }
+    Ok(events)
+}
+
+// MARK: CSV
+
+/// Parse `t,x,y,p` lines, empty lines, `#` comments and a header row before the first event
+/// are skipped.
+///
+/// The polarity column accepts `1`/`0`, `1`/`-1` and `true`/`false`.
+pub fn parse_csv(text: &str) -> Result<Vec<Event>, ReadError> {
+    let mut events = vec![];
+    let mut is_first = true;
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line
+            .split([',', ' ', '\t'])
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        if std::mem::take(&mut is_first)
+            && fields.first().is_some_and(|s| s.parse::<f64>().is_err())
+        {
+            continue;
+        }
+        let err = |reason: &str| malformed(EventFormat::Csv, format!("line {}: {reason}", idx + 1));
+        let [t, x, y, p] = fields[..] else {
+            return Err(err("expected 4 columns `t,x,y,p`"));
+        };
+        let t = t.parse::<u64>().map_err(|_| err("invalid timestamp"))?;
+        let x = x.parse::<u16>().map_err(|_| err("invalid x"))?;
+        let y = y.parse::<u16>().map_err(|_| err("invalid y"))?;
+        let polarity = match p {
+            "1" | "true" => Polarity::On,
+            "0" | "-1" | "false" => Polarity::Off,
+            _ => return Err(err("invalid polarity")),
+        };
+        events.push(Event::new(t, x, y, polarity));
+    }
+    Ok(events)
+}
+
+// MARK: Crop
+
+/// A `width x height` sub-window of the sensor, events inside it are re-based to its origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub x: u16,
+    pub y: u16,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Crop {
+    pub fn new(x: u16, y: u16, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    /// A `width x height` window at the center of the area covered by `events`.
+    pub fn centered(events: &[Event], width: usize, height: usize) -> Self {
+        let (sensor_width, sensor_height) = events.iter().fold((0, 0), |(w, h), event| {
+            (w.max(event.x as usize + 1), h.max(event.y as usize + 1))
+        });
+        Self::new(
+            (sensor_width.saturating_sub(width) / 2) as u16,
+            (sensor_height.saturating_sub(height) / 2) as u16,
+            width,
+            height,
+        )
+    }
+    pub fn contains(&self, event: &Event) -> bool {
+        event.x >= self.x
+            && event.y >= self.y
+            && ((event.x - self.x) as usize) < self.width
+            && ((event.y - self.y) as usize) < self.height
+    }
+    /// Keep the events inside the window, with coordinates relative to its top-left corner.
+    pub fn apply(self, events: impl IntoIterator<Item = Event>) -> impl Iterator<Item = Event> {
+        events
+            .into_iter()
+            .filter(move |event| self.contains(event))
+            .map(move |event| Event {
+                x: event.x - self.x,
+                y: event.y - self.y,
+                ..event
+            })
+    }
+}
@@ -0,0 +1,287 @@
+//! Events as short-lived particles.
+//!
+//! Each event spawns a dot at its pixel, which shrinks and fades out over a lifetime in event
+//! time, so the stream looks like rain on the sensor. The particles are stored in shared
+//! chunks in time order, and the particles since the last snapshot are sealed into a chunk by
+//! the next event, so the snapshots don't copy any of them. The invisible particles are
+//! skipped as soon as they expire, and a chunk is dropped once all its particles are invisible.
+
+use std::{collections::VecDeque, f64::consts::PI, sync::Arc};
+
+use ranim::{
+    color::palettes::manim,
+    components::Anchor,
+    glam::{DVec3, dvec3},
+    items::vitem::VItem,
+    prelude::*,
+    render::primitives::{Extract, vitem::VItemPrimitive},
+};
+use rayon::prelude::*;
+
+use crate::{
+    event::{Event, EventSink, OutOfBounds, Polarity, SensorGeometry},
+    frame::{PixelGrid, impl_grid_traits},
+};
+
+/// The particles are sealed into a shared chunk at least every this many events
+const CHUNK_LEN: usize = 4096;
+/// The quadratic segments of a dot
+const DOT_SEGMENTS: usize = 8;
+
+/// A dot for every event, colored by polarity, shrinking and fading out over `lifetime`.
+///
+/// The events should come in time order, the clock is the time of the latest event and can
+/// be advanced without events with [`EventScatter::set_now`].
+#[derive(Clone)]
+pub struct EventScatter {
+    grid: PixelGrid,
+    /// How long a dot takes to disappear, in event time
+    lifetime: u64,
+    /// The radius of a new dot, relative to the pixel size
+    dot_scale: f64,
+    /// The sealed particles, oldest first
+    chunks: VecDeque<Arc<Vec<Event>>>,
+    /// The particles after the sealed ones, sealed by the next event once a snapshot shares them
+    pending: Arc<Vec<Event>>,
+    /// The count of the invisible particles at the front of the oldest chunk, or of the
+    /// pending particles without any chunk, which are skipped until they are dropped
+    expired: usize,
+    now: u64,
+}
+
+impl EventScatter {
+    pub fn new(width: usize, height: usize, lifetime: u64) -> Self {
+        Self {
+            grid: PixelGrid::new(width, height),
+            lifetime,
+            dot_scale: 0.4,
+            chunks: VecDeque::new(),
+            pending: Arc::default(),
+            expired: 0,
+            now: 0,
+        }
+    }
+    pub fn fit_in(&mut self, center: DVec3, width: f64, height: f64) -> &mut Self {
+        self.grid.fit_in(center, width, height);
+        self
+    }
+    pub fn set_lifetime(&mut self, lifetime: u64) -> &mut Self {
+        self.lifetime = lifetime;
+        self.drop_expired();
+        self
+    }
+    pub fn set_dot_scale(&mut self, dot_scale: f64) -> &mut Self {
+        self.dot_scale = dot_scale;
+        self
+    }
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+    /// Advance the clock without any event, so that the dots keep fading.
+    ///
+    /// The clock never goes backwards.
+    pub fn set_now(&mut self, now: u64) -> &mut Self {
+        if now > self.now {
+            self.now = now;
+            self.drop_expired();
+        }
+        self
+    }
+    /// The count of the visible particles
+    pub fn live_cnt(&self) -> usize {
+        self.particles()
+            .filter(|event| self.is_alive(event))
+            .count()
+    }
+    /// The stored particles after the expired ones, oldest first.
+    fn particles(&self) -> impl Iterator<Item = &Event> {
+        let mut segments = self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.as_slice())
+            .chain([self.pending.as_slice()]);
+        let oldest = segments.next().map(|oldest| &oldest[self.expired..]);
+        oldest.into_iter().chain(segments).flatten()
+    }
+    fn is_alive(&self, event: &Event) -> bool {
+        self.now.saturating_sub(event.t) < self.lifetime
+    }
+    /// Drop the chunks whose newest particle is invisible and skip the invisible particles at
+    /// the front of the rest.
+    ///
+    /// The chunks are shared with the snapshots so they are never trimmed, and the pending
+    /// particles are only compacted once half of them are invisible.
+    fn drop_expired(&mut self) {
+        while let Some(chunk) = self.chunks.front()
+            && chunk.last().is_none_or(|event| !self.is_alive(event))
+        {
+            self.chunks.pop_front();
+        }
+        let oldest = self
+            .chunks
+            .front()
+            .map_or(self.pending.as_slice(), |chunk| chunk.as_slice());
+        // In time order, the invisible particles come first.
+        self.expired = oldest.partition_point(|event| !self.is_alive(event));
+        if self.chunks.is_empty() && self.expired > 0 && self.expired * 2 >= self.pending.len() {
+            match Arc::get_mut(&mut self.pending) {
+                Some(pending) => {
+                    pending.drain(..self.expired);
+                }
+                // Shared with a snapshot, only the visible particles are copied.
+                None => self.pending = Arc::new(self.pending[self.expired..].to_vec()),
+            }
+            self.expired = 0;
+        }
+    }
+    /// Move the pending particles into a new chunk.
+    fn seal(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        if !pending.is_empty() {
+            self.chunks.push_back(pending);
+        }
+    }
+    /// A filled circle of `DOT_SEGMENTS` quadratic segments.
+    fn dot(&self, center: DVec3, radius: f64, color: color::AlphaColor<color::Srgb>) -> VItem {
+        let step = 2.0 * PI / DOT_SEGMENTS as f64;
+        // The handle is where the tangents of two neighbouring anchors meet.
+        let handle_radius = radius / (step / 2.0).cos();
+        let at = |angle: f64, radius: f64| center + dvec3(angle.cos(), angle.sin(), 0.0) * radius;
+        let mut vpoints = vec![at(0.0, radius)];
+        for i in 0..DOT_SEGMENTS {
+            let angle = i as f64 * step;
+            vpoints.extend([
+                at(angle + step / 2.0, handle_radius),
+                at(angle + step, radius),
+            ]);
+        }
+        VItem::from_vpoints(vpoints).with(|dot| {
+            dot.set_fill_color(color)
+                .set_stroke_color(color.with_alpha(0.0));
+        })
+    }
+}
+
+impl EventSink for EventScatter {
+    fn geometry(&self) -> SensorGeometry {
+        self.grid.geometry()
+    }
+    fn accept(&mut self, event: &Event) -> Result<(), OutOfBounds> {
+        self.grid.geometry().index(event)?;
+        if Arc::get_mut(&mut self.pending).is_none() {
+            self.seal();
+        }
+        Arc::get_mut(&mut self.pending).unwrap().push(*event);
+        if self.pending.len() >= CHUNK_LEN {
+            self.seal();
+        }
+        self.now = self.now.max(event.t);
+        self.drop_expired();
+        Ok(())
+    }
+}
+
+impl_grid_traits!(EventScatter);
+
+impl Extract for EventScatter {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let lifetime = self.lifetime.max(1) as f64;
+        let particles = self
+            .particles()
+            .filter(|event| self.is_alive(event))
+            .collect::<Vec<_>>();
+        particles
+            .par_iter()
+            .map(|event| {
+                let life = 1.0 - self.now.saturating_sub(event.t) as f64 / lifetime;
+                let color = match event.polarity {
+                    Polarity::On => manim::YELLOW_C,
+                    Polarity::Off => manim::BLUE_C,
+                };
+                let center = self.grid.pixel_center(event.x as usize, event.y as usize);
+                let radius = self.grid.pixel_size * self.dot_scale * life;
+                self.dot(center, radius, color.with_alpha(life as f32))
+                    .extract()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(scatter: &mut EventScatter, ts: std::ops::Range<u64>) {
+        for t in ts {
+            let event = Event::new(t, (t % 4) as u16, 0, Polarity::On);
+            scatter.accept(&event).unwrap();
+        }
+    }
+
+    #[test]
+    fn counts_the_visible_particles() {
+        let mut scatter = EventScatter::new(4, 4, 10);
+        assert_eq!(scatter.live_cnt(), 0);
+        fill(&mut scatter, 0..8);
+        assert_eq!(scatter.live_cnt(), 8);
+        // Alive up to `lifetime` after its time, exclusive.
+        scatter.set_now(12);
+        assert_eq!(scatter.live_cnt(), 5);
+        assert_eq!(scatter.extract().len(), 5);
+        // The clock doesn't go back.
+        scatter.set_now(3);
+        assert_eq!((scatter.now(), scatter.live_cnt()), (12, 5));
+        scatter.set_now(17);
+        assert_eq!(scatter.live_cnt(), 0);
+        assert!(scatter.pending.is_empty());
+    }
+
+    #[test]
+    fn expires_across_a_chunk_boundary() {
+        let len = CHUNK_LEN as u64;
+        // Long enough to seal a chunk, the pending particles are compacted before otherwise.
+        let mut scatter = EventScatter::new(4, 4, 2 * len);
+        fill(&mut scatter, 0..len + 10);
+        assert_eq!((scatter.chunks.len(), scatter.pending.len()), (1, 10));
+        scatter.set_lifetime(100);
+        // The particles since `len - 90` are alive, in the chunk and after it.
+        assert_eq!(scatter.live_cnt(), 100);
+        assert_eq!(scatter.expired, CHUNK_LEN - 90);
+        assert_eq!(scatter.particles().next().unwrap().t, len - 90);
+
+        // Skipping the front of a shared chunk doesn't copy it.
+        let snapshot = scatter.clone();
+        scatter.set_now(len + 50);
+        assert!(Arc::ptr_eq(&scatter.chunks[0], &snapshot.chunks[0]));
+        assert_eq!(scatter.live_cnt(), 59);
+
+        // Once the whole chunk is invisible it is dropped with the expired pending particles.
+        scatter.set_now(len + 105);
+        assert!(scatter.chunks.is_empty());
+        assert_eq!(scatter.live_cnt(), 4);
+        assert_eq!(scatter.pending.len(), 4);
+        assert_eq!(snapshot.live_cnt(), 100);
+    }
+
+    #[test]
+    fn snapshots_share_the_pending_particles() {
+        let mut scatter = EventScatter::new(4, 4, 100);
+        fill(&mut scatter, 0..10);
+        let snapshot = scatter.clone();
+        assert!(Arc::ptr_eq(&scatter.pending, &snapshot.pending));
+        // The next event seals the shared particles instead of copying them.
+        fill(&mut scatter, 10..12);
+        assert!(Arc::ptr_eq(&scatter.chunks[0], &snapshot.pending));
+        assert_eq!(scatter.pending.len(), 2);
+        assert_eq!((scatter.live_cnt(), snapshot.live_cnt()), (12, 10));
+
+        // The chunk of the snapshot expires like any other.
+        scatter.set_now(105);
+        assert_eq!(scatter.live_cnt(), 6);
+        assert_eq!(scatter.particles().next().unwrap().t, 6);
+        scatter.set_now(110);
+        assert!(scatter.chunks.is_empty());
+        assert_eq!(scatter.live_cnt(), 1);
+    }
+}
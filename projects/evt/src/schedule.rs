@@ -0,0 +1,278 @@
+use ranim::{
+    animation::{AnimationSpan, EvalDynamic, Evaluator},
+    items::ItemId,
+    prelude::*,
+    timeline::{ItemTimeline, TimelinesFunc},
+};
+
+/// The events that become visible at the same output frame.
+#[derive(Debug, Clone)]
+pub struct FrameBatch<E> {
+    /// The index of the frame, counted from the first event
+    pub frame: usize,
+    pub events: Vec<E>,
+}
+
+/// Maps event time to scene time and groups the events of a stream by output frame.
+///
+/// An event at event time `t` is shown at scene time `(t - t0) / speed` after the start, where
+/// `t0` is the time of the first event. It is put in the first frame at or after that time, so
+/// that each frame shows exactly the events that per-event playback would have shown by then.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameScheduler {
+    pub fps: u32,
+    /// Event time units per scene second
+    pub speed: f64,
+}
+
+impl FrameScheduler {
+    pub fn new(fps: u32, speed: f64) -> Self {
+        Self { fps, speed }
+    }
+    /// A scheduler that plays `duration` event time units in `secs` scene seconds.
+    pub fn fit(fps: u32, duration: u64, secs: f64) -> Self {
+        Self::new(fps, (duration as f64 / secs).max(f64::MIN_POSITIVE))
+    }
+    pub fn frame_secs(&self) -> f64 {
+        1.0 / self.fps as f64
+    }
+    /// The frame that first shows an event `dt` event time units after the first one.
+    pub fn frame_of(&self, dt: u64) -> usize {
+        // Rounding off the float error so an event exactly on a frame boundary stays in it.
+        let frames = dt as f64 / self.speed * self.fps as f64;
+        (frames - 1e-9).ceil().max(0.0) as usize
+    }
+
+    /// Group the events by frame, `time_of` gives the event time of an event.
+    ///
+    /// The events should be sorted by time, an event earlier than its predecessor is put in
+    /// the frame of its predecessor.
+    pub fn batches<E>(
+        &self,
+        events: impl IntoIterator<Item = E>,
+        time_of: impl Fn(&E) -> u64,
+    ) -> Vec<FrameBatch<E>> {
+        let mut batches: Vec<FrameBatch<E>> = vec![];
+        let mut t0 = None;
+        for event in events {
+            let t = time_of(&event);
+            let t0 = *t0.get_or_insert(t);
+            let frame = self.frame_of(t.saturating_sub(t0));
+            match batches.last_mut() {
+                Some(batch) if batch.frame >= frame => batch.events.push(event),
+                _ => batches.push(FrameBatch {
+                    frame,
+                    events: vec![event],
+                }),
+            }
+        }
+        batches
+    }
+
+    /// Play the events on the timeline of `item_id`, starting from the current time of the
+    /// timeline, with one update per frame that has events.
+    ///
+    /// Only the timeline of `item_id` is forwarded, sync the timelines afterwards if needed.
+    pub fn play<T: Clone + 'static, E>(
+        &self,
+        r: &mut RanimScene,
+        item_id: &ItemId<T>,
+        events: impl IntoIterator<Item = E>,
+        time_of: impl Fn(&E) -> u64,
+        mut apply: impl FnMut(&mut T, &E),
+    ) {
+        let timeline: &mut ItemTimeline<T> = r.timeline_mut(item_id);
+        let start_sec = timeline.cur_sec();
+        let mut last_frame = 0;
+        for batch in self.batches(events, time_of) {
+            timeline.forward_to(start_sec + batch.frame as f64 * self.frame_secs());
+            timeline.update_with(|item| {
+                batch.events.iter().for_each(|event| apply(item, event));
+            });
+            last_frame = batch.frame;
+        }
+        // Keep the last state on screen for a frame.
+        timeline.forward_to(start_sec + (last_frame + 1) as f64 * self.frame_secs());
+    }
+
+    /// Play the events on all timelines, `on_frame` gets the events of each frame that has
+    /// events and can update several items for it.
+    ///
+    /// The timelines are synced first and are all forwarded together.
+    pub fn play_frames<E>(
+        &self,
+        r: &mut RanimScene,
+        events: impl IntoIterator<Item = E>,
+        time_of: impl Fn(&E) -> u64,
+        mut on_frame: impl FnMut(&mut RanimScene, &[E]),
+    ) {
+        r.timelines_mut().sync();
+        let mut last_frame = 0;
+        for batch in self.batches(events, time_of) {
+            r.timelines_mut()
+                .forward((batch.frame - last_frame) as f64 * self.frame_secs());
+            on_frame(r, &batch.events);
+            last_frame = batch.frame;
+        }
+        r.timelines_mut().forward(self.frame_secs());
+    }
+
+    /// Like [`FrameScheduler::play`], and the clock of the item keeps running between the
+    /// frames that have events, so that anything depending on the current time, like the
+    /// sliding window of a [`crate::TimeSurface`], changes even without new events.
+    ///
+    /// `set_clock` sets the current event time of the item, it is called for every rendered
+    /// frame between the updates.
+    pub fn play_clocked<T: Clone + 'static, E>(
+        &self,
+        r: &mut RanimScene,
+        item_id: &ItemId<T>,
+        events: impl IntoIterator<Item = E>,
+        time_of: impl Fn(&E) -> u64,
+        mut apply: impl FnMut(&mut T, &E),
+        set_clock: impl Fn(&mut T, u64) + Clone + 'static,
+    ) {
+        let timeline: &mut ItemTimeline<T> = r.timeline_mut(item_id);
+        let batches = self.batches(events, &time_of);
+        let Some(t0) = batches.first().map(|batch| time_of(&batch.events[0])) else {
+            return;
+        };
+        let clock_at = |frame: usize| t0 + (frame as f64 * self.frame_secs() * self.speed) as u64;
+
+        let mut last_frame = None;
+        let tick = |timeline: &mut ItemTimeline<T>, from: usize, to: usize| {
+            let tick = Tick {
+                state: timeline.snapshot(),
+                from: clock_at(from),
+                to: clock_at(to),
+                set_clock: set_clock.clone(),
+            };
+            timeline.play(
+                AnimationSpan::from_evaluator(Evaluator::new_dynamic(tick))
+                    .with_duration((to - from) as f64 * self.frame_secs()),
+            );
+        };
+        for batch in batches {
+            if let Some(last_frame) = last_frame.filter(|&last_frame| batch.frame > last_frame) {
+                tick(timeline, last_frame, batch.frame);
+            }
+            timeline.update_with(|item| {
+                batch.events.iter().for_each(|event| apply(item, event));
+            });
+            last_frame = Some(batch.frame);
+        }
+        // Let the clock run for a frame after the last event.
+        let last_frame = last_frame.unwrap();
+        tick(timeline, last_frame, last_frame + 1);
+    }
+}
+
+/// Running the clock of an item from `from` to `to`.
+struct Tick<T, F> {
+    state: T,
+    from: u64,
+    to: u64,
+    set_clock: F,
+}
+
+impl<T: Clone, F: Fn(&mut T, u64)> EvalDynamic<T> for Tick<T, F> {
+    fn eval_alpha(&self, alpha: f64) -> T {
+        let mut state = self.state.clone();
+        let now = self.from + ((self.to - self.from) as f64 * alpha).round() as u64;
+        (self.set_clock)(&mut state, now);
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    /// The frames of the batches and the events in each.
+    fn frames(scheduler: &FrameScheduler, ts: &[u64]) -> Vec<(usize, Vec<u64>)> {
+        scheduler
+            .batches(ts.iter().copied(), |&t| t)
+            .into_iter()
+            .map(|batch| (batch.frame, batch.events))
+            .collect()
+    }
+
+    #[test]
+    fn boundary_events_stay_in_their_frame() {
+        // 10 event time units per frame.
+        let scheduler = FrameScheduler::new(60, 600.0);
+        assert_eq!(scheduler.frame_of(0), 0);
+        assert_eq!(scheduler.frame_of(10), 1);
+        assert_eq!(scheduler.frame_of(11), 2);
+        // 100 / 9 units per frame, where `100 / speed * fps` is not exactly 9.
+        let scheduler = FrameScheduler::new(30, 1000.0 / 3.0);
+        assert_eq!(scheduler.frame_of(100), 9);
+        assert_eq!(scheduler.frame_of(101), 10);
+    }
+
+    #[test]
+    fn batches_are_rebased_on_the_first_event() {
+        let scheduler = FrameScheduler::new(60, 600.0);
+        let t0 = 1_700_000_000;
+        assert_eq!(
+            frames(
+                &scheduler,
+                &[t0, t0 + 3, t0 + 10, t0 + 11, t0 + 19, t0 + 20]
+            ),
+            vec![
+                (0, vec![t0]),
+                (1, vec![t0 + 3, t0 + 10]),
+                (2, vec![t0 + 11, t0 + 19, t0 + 20]),
+            ]
+        );
+    }
+
+    #[test]
+    fn frames_without_events_are_skipped() {
+        let scheduler = FrameScheduler::new(60, 600.0);
+        assert_eq!(
+            frames(&scheduler, &[5, 5, 60, 61, 200]),
+            vec![(0, vec![5, 5]), (6, vec![60, 61]), (20, vec![200])]
+        );
+        assert!(frames(&scheduler, &[]).is_empty());
+        // An event out of order joins the frame of its predecessor.
+        assert_eq!(
+            frames(&scheduler, &[0, 50, 20, 51]),
+            vec![(0, vec![0]), (5, vec![50, 20]), (6, vec![51])]
+        );
+    }
+
+    #[test]
+    fn frames_show_what_per_event_playback_shows() {
+        let scheduler = FrameScheduler::fit(24, 5000, 7.0);
+        let ts = (0..500u64)
+            .map(|i| 3 + i * i % 4999)
+            .sorted()
+            .collect::<Vec<_>>();
+        for batch in scheduler.batches(ts.iter().copied(), |&t| t) {
+            let end = batch.frame as f64 * scheduler.frame_secs();
+            for t in batch.events {
+                let sec = (t - ts[0]) as f64 / scheduler.speed;
+                assert!(sec <= end + 1e-9, "{t} at {sec}s after the frame at {end}s");
+                assert!(
+                    sec > end - scheduler.frame_secs(),
+                    "{t} at {sec}s before its frame"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fit_plays_the_duration_in_the_seconds() {
+        let scheduler = FrameScheduler::fit(60, 3000, 6.0);
+        assert_eq!(scheduler.speed, 500.0);
+        assert_eq!(scheduler.frame_of(3000), 360);
+        let scheduler = FrameScheduler::fit(30, 1000, 3.0);
+        assert_eq!(scheduler.frame_of(1000), 90);
+        assert_eq!(scheduler.frame_of(1001), 91);
+        // A zero duration doesn't divide by zero.
+        assert!(FrameScheduler::fit(60, 0, 1.0).speed > 0.0);
+    }
+}
@@ -0,0 +1,328 @@
+//! An event simulator for ordinary video, in the manner of v2e and ESIM.
+//!
+//! Each pixel keeps the log intensity at its last event. When a new frame moves the log
+//! intensity of a pixel by a contrast threshold or more from there, the pixel fires one event
+//! per threshold crossed, ON for brighter and OFF for darker. The intensity is taken to change
+//! linearly between two frames, so the events are spread over the frame interval at the times
+//! their thresholds are crossed.
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::event::{Event, Polarity};
+
+#[derive(Debug)]
+pub enum FrameError {
+    Io(io::Error),
+    Decode {
+        path: PathBuf,
+        err: image::ImageError,
+    },
+    /// A frame whose size differs from the first one
+    SizeMismatch {
+        path: PathBuf,
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Io(err) => write!(f, "io error: {err}"),
+            FrameError::Decode { path, err } => {
+                write!(f, "failed to decode {}: {err}", path.display())
+            }
+            FrameError::SizeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} is {}x{}, expected {}x{}",
+                path.display(),
+                found.0,
+                found.1,
+                expected.0,
+                expected.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FrameError::Io(err) => Some(err),
+            FrameError::Decode { err, .. } => Some(err),
+            FrameError::SizeMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for FrameError {
+    fn from(err: io::Error) -> Self {
+        FrameError::Io(err)
+    }
+}
+
+/// A grayscale frame, the intensities are in [0.0, 1.0] and row 0 is at the top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrayFrame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<f32>,
+}
+
+impl GrayFrame {
+    pub fn new(width: usize, height: usize, pixels: Vec<f32>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel count mismatch");
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+    /// A frame with the intensity `f(x, y)` at each pixel.
+    pub fn from_fn(width: usize, height: usize, f: impl Fn(usize, usize) -> f32) -> Self {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+        Self::new(width, height, pixels)
+    }
+    /// Read an image as its luma, the stored (gamma encoded) values are used as they are.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, FrameError> {
+        let path = path.as_ref();
+        let image = image::open(path)
+            .map_err(|err| match err {
+                image::ImageError::IoError(err) => FrameError::Io(err),
+                err => FrameError::Decode {
+                    path: path.to_path_buf(),
+                    err,
+                },
+            })?
+            .to_luma32f();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        Ok(Self::new(width, height, image.into_raw()))
+    }
+    pub fn intensity(&self, x: usize, y: usize) -> f32 {
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Read the `.png` frames of a directory in the order of their file names, so the names
+/// should be zero padded like `frame_0001.png`.
+pub fn read_png_frames(dir: impl AsRef<Path>) -> Result<Vec<GrayFrame>, FrameError> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+    });
+    paths.sort();
+
+    let mut frames: Vec<GrayFrame> = vec![];
+    for path in paths {
+        let frame = GrayFrame::read(&path)?;
+        if let Some(first) = frames.first()
+            && (first.width, first.height) != (frame.width, frame.height)
+        {
+            return Err(FrameError::SizeMismatch {
+                path,
+                expected: (first.width, first.height),
+                found: (frame.width, frame.height),
+            });
+        }
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// Turns a sequence of frames into events, see the [module docs](self).
+///
+/// The first frame only sets the reference of each pixel, the events of a later frame are in
+/// the interval between it and the frame before.
+#[derive(Debug, Clone)]
+pub struct EventSimulator {
+    /// The log intensity step of an ON event
+    pub threshold_on: f32,
+    /// The log intensity step of an OFF event
+    pub threshold_off: f32,
+    /// The time between two frames, in microseconds like [`Event::t`]
+    pub frame_interval: u64,
+    /// Added to the intensity before taking the log, so that black doesn't go to -inf
+    pub log_eps: f32,
+    /// The log intensity at the last event of each pixel
+    reference: Vec<f32>,
+    /// The log intensity of each pixel in the last frame
+    last: Vec<f32>,
+    width: usize,
+    height: usize,
+    /// The time of the last frame
+    t: u64,
+}
+
+impl EventSimulator {
+    /// `threshold` is the contrast threshold of both polarities, v2e defaults to 0.2.
+    pub fn new(threshold: f32, frame_interval: u64) -> Self {
+        Self {
+            threshold_on: threshold,
+            threshold_off: threshold,
+            frame_interval,
+            log_eps: 1e-3,
+            reference: vec![],
+            last: vec![],
+            width: 0,
+            height: 0,
+            t: 0,
+        }
+    }
+    pub fn with_thresholds(mut self, threshold_on: f32, threshold_off: f32) -> Self {
+        self.threshold_on = threshold_on;
+        self.threshold_off = threshold_off;
+        self
+    }
+    pub fn with_log_eps(mut self, log_eps: f32) -> Self {
+        self.log_eps = log_eps;
+        self
+    }
+    /// The time of the last frame pushed.
+    pub fn t(&self) -> u64 {
+        self.t
+    }
+    /// Push the next frame and get the events since the frame before, sorted by time.
+    ///
+    /// A frame of another size than the first one restarts the simulation at its time.
+    pub fn push_frame(&mut self, frame: &GrayFrame) -> Vec<Event> {
+        let log = frame
+            .pixels
+            .iter()
+            .map(|&intensity| (intensity.max(0.0) + self.log_eps).ln())
+            .collect::<Vec<_>>();
+        if self.last.is_empty() || (self.width, self.height) != (frame.width, frame.height) {
+            self.width = frame.width;
+            self.height = frame.height;
+            self.reference = log.clone();
+            self.last = log;
+            return vec![];
+        }
+
+        let t0 = self.t;
+        self.t += self.frame_interval;
+        let (threshold_on, threshold_off) = (
+            self.threshold_on.max(f32::EPSILON),
+            self.threshold_off.max(f32::EPSILON),
+        );
+        let mut events = vec![];
+        for (idx, (&from, &to)) in self.last.iter().zip(&log).enumerate() {
+            let (x, y) = ((idx % self.width) as u16, (idx / self.width) as u16);
+            let reference = &mut self.reference[idx];
+            // The time at which the linear change from `from` to `to` reaches `level`.
+            let time_of = |level: f32| {
+                let alpha = ((level - from) / (to - from)).clamp(0.0, 1.0) as f64;
+                t0 + (alpha * self.frame_interval as f64).round() as u64
+            };
+            while to - *reference >= threshold_on {
+                *reference += threshold_on;
+                events.push(Event::new(time_of(*reference), x, y, Polarity::On));
+            }
+            while *reference - to >= threshold_off {
+                *reference -= threshold_off;
+                events.push(Event::new(time_of(*reference), x, y, Polarity::Off));
+            }
+        }
+        self.last = log;
+        events.sort();
+        events
+    }
+    /// Simulate the whole sequence, the first frame is at time 0.
+    pub fn simulate<'a>(&mut self, frames: impl IntoIterator<Item = &'a GrayFrame>) -> Vec<Event> {
+        frames
+            .into_iter()
+            .flat_map(|frame| self.push_frame(frame))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3x1 frame, the log intensity of each pixel is `ln(0.1)` plus its step.
+    fn frame(steps: [f32; 3]) -> GrayFrame {
+        GrayFrame::from_fn(3, 1, |x, _| 0.1 * steps[x].exp())
+    }
+
+    #[test]
+    fn push_frame_crosses_thresholds() {
+        let mut simulator = EventSimulator::new(0.2, 1000).with_log_eps(0.0);
+        assert!(simulator.push_frame(&frame([0.0; 3])).is_empty());
+        assert_eq!(simulator.t(), 0);
+
+        // Two ON crossings at 0.2 and 0.4 of 0.5, one OFF crossing at 0.2 of 0.3.
+        let events = simulator.push_frame(&frame([0.5, 0.0, -0.3]));
+        assert_eq!(
+            events,
+            vec![
+                Event::new(400, 0, 0, Polarity::On),
+                Event::new(667, 2, 0, Polarity::Off),
+                Event::new(800, 0, 0, Polarity::On),
+            ]
+        );
+        assert_eq!(simulator.t(), 1000);
+
+        // The reference is the level of the last event, not the last frame, so 0.1 more is
+        // enough for the next crossing, halfway through the interval.
+        let events = simulator.push_frame(&frame([0.7, 0.0, -0.3]));
+        assert_eq!(events, vec![Event::new(1500, 0, 0, Polarity::On)]);
+    }
+
+    #[test]
+    fn push_frame_restarts_on_another_size() {
+        let mut simulator = EventSimulator::new(0.2, 1000);
+        simulator.push_frame(&frame([0.0; 3]));
+        assert!(
+            simulator
+                .push_frame(&GrayFrame::from_fn(2, 2, |_, _| 1.0))
+                .is_empty()
+        );
+        assert_eq!(simulator.t(), 0);
+    }
+
+    #[test]
+    fn read_png_frames_checks_the_size() {
+        let dir = std::env::temp_dir().join(format!("evt-frames-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let save = |name: &str, width: u32, height: u32| {
+            image::GrayImage::from_pixel(width, height, image::Luma([255]))
+                .save(dir.join(name))
+                .unwrap();
+        };
+        save("frame_0001.png", 4, 3);
+        save("frame_0000.png", 4, 3);
+        fs::write(dir.join("notes.txt"), "not a frame").unwrap();
+        let frames = read_png_frames(&dir);
+        save("frame_0002.png", 3, 4);
+        let mismatch = read_png_frames(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let frames = frames.unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], GrayFrame::from_fn(4, 3, |_, _| 1.0));
+        match mismatch {
+            Err(FrameError::SizeMismatch {
+                path,
+                expected,
+                found,
+            }) => {
+                assert_eq!(path, dir.join("frame_0002.png"));
+                assert_eq!((expected, found), ((4, 3), (3, 4)));
+            }
+            other => panic!("expected a size mismatch, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,1691 @@
+//! The time surface, a grid of cells showing the last event of each pixel.
+//!
+//! Events are fed one by one through a filter, and each cell keeps the time of its last
+//! accepted event, which is drawn as the color of its square. The cells are shared between the
+//! snapshots of the surface, so that an update only copies the cells it touches.
+
+mod layers;
+
+use std::{borrow::Cow, collections::VecDeque, sync::Arc};
+
+use cached_extract::CachedExtract;
+use itertools::Itertools;
+use ranim::{
+    color::palettes::manim,
+    components::Anchor,
+    glam::{DVec3, dvec3},
+    items::vitem::{
+        VItem,
+        geometry::{Polygon, Square},
+    },
+    prelude::*,
+    render::primitives::{Extract, vitem::VItemPrimitive},
+};
+use rayon::prelude::*;
+
+use crate::{
+    FRAME_SIZE,
+    colormap::Colormap,
+    corner::CornerDetector,
+    event::{Event, EventSink, OutOfBounds, Polarity, SensorGeometry},
+    filter::{EventFilter, PassThrough, TimestampMap},
+    flow::{Flow, PlaneFit},
+    frame::square_scale,
+    generator::LabeledEvent,
+    glyph::glyph_label,
+    metrics::DenoiseMetrics,
+    normalize::{Normalization, SharedReference, reference_times},
+    persistent::PersistentVec,
+};
+use layers::{CornerLayer, FlowLayer, HeightField, HistoryLayer, Layers};
+
+#[derive(Clone)]
+struct TimeSurfaceCell {
+    start: DVec3,
+    cell_size: f64,
+    y: usize,
+    x: usize,
+    /// The time of the last event, whether it is accepted or not
+    t: Option<usize>,
+    /// The time of the last accepted event
+    real_t: usize,
+    /// The time of the last accepted ON / OFF event
+    on_t: Option<usize>,
+    off_t: Option<usize>,
+    /// The polarity of the last accepted event
+    polarity: Option<Polarity>,
+    panels: Arc<Vec<Panel>>,
+    style: Arc<CellStyle>,
+    /// The decision of the last event
+    decision: Option<CellDecision>,
+}
+
+impl TimeSurfaceCell {
+    pub fn new(start: DVec3, cell_size: f64, y: usize, x: usize) -> Self {
+        Self {
+            start,
+            cell_size,
+            y,
+            x,
+            t: None,
+            real_t: 0,
+            on_t: None,
+            off_t: None,
+            polarity: None,
+            panels: Arc::new(vec![Panel::MERGED]),
+            style: Arc::new(CellStyle::default()),
+            decision: None,
+        }
+    }
+    pub fn set_t(&mut self, t: usize) {
+        self.t = Some(t);
+    }
+    pub fn accept(&mut self, real_t: usize, polarity: Polarity) {
+        self.real_t = real_t;
+        match polarity {
+            Polarity::On => self.on_t = Some(real_t),
+            Polarity::Off => self.off_t = Some(real_t),
+        }
+        self.polarity = Some(polarity);
+    }
+    /// The time of the last accepted event of the channel, `None` is the merged channel.
+    pub fn channel_t(&self, channel: Option<Polarity>) -> Option<usize> {
+        match channel {
+            None => self.polarity.map(|_| self.real_t),
+            Some(Polarity::On) => self.on_t,
+            Some(Polarity::Off) => self.off_t,
+        }
+    }
+    /// The color ramp of the channel, the merged channel follows the last accepted polarity.
+    pub fn channel_ramp(
+        &self,
+        channel: Option<Polarity>,
+    ) -> (
+        color::AlphaColor<color::Srgb>,
+        color::AlphaColor<color::Srgb>,
+    ) {
+        match channel.or(self.polarity) {
+            Some(Polarity::On) => (manim::GOLD_E, manim::YELLOW_C),
+            Some(Polarity::Off) | None => (manim::BLUE_E, manim::BLUE_A),
+        }
+    }
+    pub fn set_panels(&mut self, panels: Arc<Vec<Panel>>) {
+        self.panels = panels;
+    }
+    pub fn set_style(&mut self, style: Arc<CellStyle>) {
+        self.style = style;
+    }
+    /// A square of `size` with the corner radius of the style.
+    fn shape(&self, size: f64) -> VItem {
+        let radius = (self.style.corner_radius * size).clamp(0.0, size / 2.0);
+        if radius <= 0.0 {
+            return VItem::from(Square::new(size));
+        }
+        let (h, r) = (size / 2.0, radius);
+        // Each side is a line and each corner is a quadratic curve, starting at the bottom of
+        // the right side and going counterclockwise.
+        let corners = [
+            (dvec3(h, h, 0.0), dvec3(h, h - r, 0.0), dvec3(h - r, h, 0.0)),
+            (
+                dvec3(-h, h, 0.0),
+                dvec3(-h + r, h, 0.0),
+                dvec3(-h, h - r, 0.0),
+            ),
+            (
+                dvec3(-h, -h, 0.0),
+                dvec3(-h, -h + r, 0.0),
+                dvec3(-h + r, -h, 0.0),
+            ),
+            (
+                dvec3(h, -h, 0.0),
+                dvec3(h - r, -h, 0.0),
+                dvec3(h, -h + r, 0.0),
+            ),
+        ];
+        let mut vpoints = vec![dvec3(h, -h + r, 0.0)];
+        for (corner, from, to) in corners {
+            let last = *vpoints.last().unwrap();
+            vpoints.extend([(last + from) / 2.0, from, corner, to]);
+        }
+        VItem::from_vpoints(vpoints)
+    }
+    pub fn set_decision(&mut self, decision: CellDecision) {
+        self.decision = Some(decision);
+    }
+    /// The decision flash fades linearly from 1.0 at the decision time to 0.0 `fade_duration` later.
+    fn flash_at(&self, now: usize, fade_duration: usize) -> f32 {
+        self.decision.as_ref().map_or(0.0, |decision| {
+            (1.0 - now.saturating_sub(decision.t) as f32 / fade_duration.max(1) as f32).max(0.0)
+        })
+    }
+    fn center_of(&self, x: usize, y: usize) -> DVec3 {
+        self.start + y as f64 * DVec3::NEG_Y * self.cell_size + x as f64 * DVec3::X * self.cell_size
+    }
+    fn panel_center_of(&self, panel: &Panel, x: usize, y: usize) -> DVec3 {
+        self.center_of(x, y) * panel.scale + panel.offset
+    }
+}
+
+/// An event in the history of a cell, see [`TimeSurface::set_history_len`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub t: usize,
+    pub polarity: Polarity,
+    pub accepted: bool,
+}
+
+/// How the history of a cell is drawn inside its square.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryStyle {
+    /// A tick per event, up for ON and down for OFF, red if rejected, placed by its age before
+    /// the newest event of the cell and dropped once older than `span`
+    SpikeTrain { span: usize },
+    /// A line through the intervals between consecutive events, higher for shorter ones,
+    /// `max_interval` or longer being at the bottom
+    Sparkline { max_interval: usize },
+}
+
+/// How the cells of a [`TimeSurface`] are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellStyle {
+    /// The gap between the cells, relative to the cell size
+    pub padding_ratio: f64,
+    /// Relative to the size of the square, 0.5 makes it a circle
+    pub corner_radius: f64,
+    pub show_labels: bool,
+    /// The digit height of the labels, relative to the size of the square
+    pub label_scale: f64,
+    /// Draw the history of each cell, which needs [`TimeSurface::set_history_len`]
+    pub history: Option<HistoryStyle>,
+}
+
+impl Default for CellStyle {
+    fn default() -> Self {
+        Self {
+            padding_ratio: 0.1,
+            corner_radius: 0.0,
+            show_labels: true,
+            label_scale: 0.2,
+            history: None,
+        }
+    }
+}
+
+/// How the ON / OFF channels of a [`TimeSurface`] are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SurfaceMode {
+    /// One grid with the last accepted event of any polarity
+    #[default]
+    Merged,
+    /// One grid with only the ON events
+    On,
+    /// One grid with only the OFF events
+    Off,
+    /// The ON grid on the left and the OFF grid on the right
+    SideBySide,
+}
+
+/// A copy of the grid showing one channel, placed by scaling the grid about the origin and
+/// then shifting it by `offset`.
+#[derive(Debug, Clone, Copy)]
+pub struct Panel {
+    /// `None` is the merged channel
+    pub channel: Option<Polarity>,
+    pub scale: f64,
+    pub offset: DVec3,
+}
+
+impl Panel {
+    const MERGED: Self = Self {
+        channel: None,
+        scale: 1.0,
+        offset: DVec3::ZERO,
+    };
+}
+
+/// The last decision made for the event of a cell, with the neighbours that supported it.
+#[derive(Debug, Clone)]
+pub struct CellDecision {
+    pub t: usize,
+    pub polarity: Polarity,
+    pub accepted: bool,
+    pub supporters: Vec<(usize, usize)>,
+}
+
+// Without text: 212.1 µs
+// Without cache: 127131.2 µs
+// With cache: 110121.6 µs
+// With LRU Cache: 51117.8 µs
+// With LRU Cache and only construct world once: 2475.2 µs
+impl TimeSurfaceCell {
+    /// The squares of all panels, then the labels, the ones of the `idx`-th panel faded by
+    /// `fades[idx]`, see [`TimeSurface::set_window`].
+    fn items(&self, fades: &[f32]) -> Vec<VItem> {
+        let fade = |idx: usize| fades.get(idx).copied().unwrap_or(1.0);
+        let square_size = self.cell_size * (1.0 - self.style.padding_ratio);
+        // The squares of all panels come first, so that the surface can color them by index.
+        let squares = self.panels.iter().map(|panel| {
+            self.shape(square_size * panel.scale).with(|square| {
+                square.put_center_on(self.panel_center_of(panel, self.x, self.y));
+            })
+        });
+        let texts = self
+            .panels
+            .iter()
+            .enumerate()
+            .filter(|_| self.style.show_labels)
+            .flat_map(|(idx, panel)| {
+                glyph_label(
+                    &self.channel_t(panel.channel).unwrap_or(0).to_string(),
+                    square_size * panel.scale * self.style.label_scale,
+                    self.panel_center_of(panel, self.x, self.y),
+                )
+                .with(|text| {
+                    text.set_fill_color(manim::WHITE.with_alpha(fade(idx)));
+                })
+            });
+        squares.chain(texts).collect()
+    }
+    /// Flash green / red on accept / reject with an intensity of `flash`, with the supporting
+    /// neighbours outlined and linked to this cell, faded like [`TimeSurfaceCell::items`].
+    fn flash_items(&self, flash: f32, fades: &[f32]) -> Vec<VItem> {
+        let fade = |idx: usize| fades.get(idx).copied().unwrap_or(1.0);
+        let square_size = self.cell_size * (1.0 - self.style.padding_ratio);
+        let mut overlay = vec![];
+        if let Some(decision) = self.decision.as_ref().filter(|_| flash > 0.0) {
+            let color = if decision.accepted {
+                manim::GREEN_C
+            } else {
+                manim::RED_C
+            };
+            let panels = self.panels.iter().enumerate().filter(|(_, panel)| {
+                panel
+                    .channel
+                    .is_none_or(|channel| channel == decision.polarity)
+            });
+            for (idx, panel) in panels {
+                let start = overlay.len();
+                let pos = self.panel_center_of(panel, self.x, self.y);
+                overlay.push(self.shape(square_size * panel.scale).with(|square| {
+                    square
+                        .set_fill_color(color.with_alpha(flash * 0.6))
+                        .set_stroke_color(color.with_alpha(flash))
+                        .put_center_on(pos);
+                }));
+                for &(x, y) in &decision.supporters {
+                    let neighbour_pos = self.panel_center_of(panel, x, y);
+                    overlay.push(self.shape(square_size * panel.scale * 0.9).with(|square| {
+                        square
+                            .set_fill_color(color.with_alpha(0.0))
+                            .set_stroke_color(color.with_alpha(flash))
+                            .set_stroke_width(0.02)
+                            .put_center_on(neighbour_pos);
+                    }));
+                    overlay.push(
+                        VItem::from_vpoints(vec![pos, (pos + neighbour_pos) / 2.0, neighbour_pos])
+                            .with(|line| {
+                                line.set_stroke_color(color.with_alpha(flash))
+                                    .set_stroke_width(0.02);
+                            }),
+                    );
+                }
+                for item in &mut overlay[start..] {
+                    fade_out(item, fade(idx));
+                }
+            }
+        }
+        overlay
+    }
+}
+
+impl Extract for TimeSurfaceCell {
+    type Target = Vec<VItemPrimitive>;
+    /// The items without any window fade.
+    fn extract(&self) -> Self::Target {
+        self.items(&[]).iter().map(|item| item.extract()).collect()
+    }
+}
+
+/// The accept/reject decision made for an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub t: usize,
+    pub x: usize,
+    pub y: usize,
+    pub accepted: bool,
+    /// Whether the corner detector flagged the event, only accepted events are checked
+    pub corner: bool,
+}
+
+#[derive(Clone)]
+pub struct TimeSurface {
+    width: usize,
+    height: usize,
+    /// Shared between the snapshots of the surface, an update only copies the cells it touches
+    cells: PersistentVec<CachedExtract<TimeSurfaceCell>>,
+    filter: Arc<dyn EventFilter>,
+    last_decision: Option<Decision>,
+    accepted_cnt: usize,
+    rejected_cnt: usize,
+    /// Only counts the events fed with [`TimeSurface::accept_labeled`]
+    metrics: DenoiseMetrics,
+    /// The time of the latest event
+    now: usize,
+    /// How long the decision flash of a cell takes to fade out, in event time
+    flash_duration: usize,
+    mode: SurfaceMode,
+    panels: Arc<Vec<Panel>>,
+    style: Arc<CellStyle>,
+    normalization: Normalization,
+    /// Color the cells with a colormap instead of the polarity ramps
+    colormap: Option<Colormap>,
+    /// Normalize against these times instead of the surface's own ones
+    shared_reference: Option<Arc<SharedReference>>,
+    /// Only the events within the window before `now` are shown, fading out as they age
+    window: Option<usize>,
+    /// The history, the flow, the corners and the height field, each with its own state
+    layers: Layers,
+    /// The normalized values shown in the middle of a transform, per panel
+    blended_values: Option<Arc<Vec<Vec<f32>>>>,
+}
+
+impl TimeSurface {
+    /// Placed like [`crate::frame::PixelGrid::new`].
+    ///
+    /// See [`TimeSurface::builder`] for the placement and the style.
+    ///
+    /// Panics if `width` or `height` is 0.
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(
+            width > 0 && height > 0,
+            "a time surface needs at least one cell, got {width}x{height}"
+        );
+        let cell_size = FRAME_SIZE.y / height as f64;
+        let start = Self::start_of(DVec3::ZERO, width, height, cell_size);
+        Self {
+            width,
+            height,
+            cells: (0..height)
+                .cartesian_product(0..width)
+                .map(|(y, x)| CachedExtract::new(TimeSurfaceCell::new(start, cell_size, y, x)))
+                .collect(),
+            filter: Arc::new(PassThrough),
+            last_decision: None,
+            accepted_cnt: 0,
+            rejected_cnt: 0,
+            metrics: DenoiseMetrics::default(),
+            now: 0,
+            flash_duration: 50,
+            mode: SurfaceMode::Merged,
+            panels: Arc::new(vec![Panel::MERGED]),
+            style: Arc::new(CellStyle::default()),
+            normalization: Normalization::default(),
+            colormap: None,
+            shared_reference: None,
+            window: None,
+            layers: Layers::default(),
+            blended_values: None,
+        }
+    }
+    pub fn builder(width: usize, height: usize) -> TimeSurfaceBuilder {
+        TimeSurfaceBuilder::new(width, height)
+    }
+    pub fn geometry(&self) -> SensorGeometry {
+        SensorGeometry::new(self.width, self.height)
+    }
+    /// The center of the top left cell of a grid centered on `center`.
+    fn start_of(center: DVec3, width: usize, height: usize, cell_size: f64) -> DVec3 {
+        center
+            + dvec3(
+                -(width as f64 - 1.0) * cell_size / 2.0,
+                (height as f64 - 1.0) * cell_size / 2.0,
+                0.0,
+            )
+    }
+    fn set_layout(&mut self, start: DVec3, cell_size: f64) -> &mut Self {
+        for idx in 0..self.cells.len() {
+            let cell = self.cells.get_mut(idx).unwrap();
+            cell.start = start;
+            cell.cell_size = cell_size;
+        }
+        // The panels are placed from the layout of the grid.
+        self.set_mode(self.mode)
+    }
+    /// The center of the grid before it is split into panels.
+    fn grid_center(&self) -> DVec3 {
+        let cell = &self.cells[0];
+        cell.start
+            + dvec3(
+                (self.width as f64 - 1.0) * cell.cell_size,
+                -(self.height as f64 - 1.0) * cell.cell_size,
+                0.0,
+            ) / 2.0
+    }
+    /// Like [`crate::frame::PixelGrid::fit_in`].
+    pub fn fit_in(&mut self, center: DVec3, width: f64, height: f64) -> &mut Self {
+        let cell_size = (width / self.width as f64).min(height / self.height as f64);
+        self.set_layout(
+            Self::start_of(center, self.width, self.height, cell_size),
+            cell_size,
+        )
+    }
+    pub fn style(&self) -> CellStyle {
+        *self.style
+    }
+    pub fn set_style(&mut self, style: CellStyle) -> &mut Self {
+        self.style = Arc::new(style);
+        for idx in 0..self.cells.len() {
+            self.cells
+                .get_mut(idx)
+                .unwrap()
+                .set_style(self.style.clone());
+        }
+        self
+    }
+    /// The size of a cell of the grid
+    pub fn cell_size(&self) -> f64 {
+        self.cells[0].cell_size
+    }
+    /// The center of `(x, y)` and the size of its cell in the grid before it is split into
+    /// panels, `None` if `(x, y)` is out of the surface.
+    pub fn grid_cell_rect(&self, x: usize, y: usize) -> Option<(DVec3, f64)> {
+        let cell = &self.cells[self.geometry().index_of(x, y)?];
+        Some((cell.center_of(x, y), cell.cell_size))
+    }
+    /// The center of `(x, y)` and the size of its cell in the first panel that shows the
+    /// events of `polarity`.
+    ///
+    /// `None` if `(x, y)` is out of the surface or if no panel shows `polarity`, like the OFF
+    /// events in [`SurfaceMode::On`].
+    pub fn cell_rect(&self, x: usize, y: usize, polarity: Polarity) -> Option<(DVec3, f64)> {
+        let cell = &self.cells[self.geometry().index_of(x, y)?];
+        let panel = self
+            .panels
+            .iter()
+            .find(|panel| panel.channel.is_none_or(|channel| channel == polarity))?;
+        Some((
+            cell.panel_center_of(panel, x, y),
+            cell.cell_size * panel.scale,
+        ))
+    }
+    /// The sorted distinct times of the last accepted events of the channel.
+    pub fn channel_times(&self, channel: Option<Polarity>) -> Vec<usize> {
+        let ts = self
+            .cells
+            .iter()
+            .map(|cell| cell.channel_t(channel))
+            .collect::<Vec<_>>();
+        reference_times(&ts)
+    }
+    /// The sorted times that the channel is normalized against, the shared reference if one is
+    /// set and the [`TimeSurface::channel_times`] otherwise.
+    pub fn normalization_reference(&self, channel: Option<Polarity>) -> Vec<usize> {
+        match &self.shared_reference {
+            Some(reference) => reference.get(channel).to_vec(),
+            None => self.channel_times(channel),
+        }
+    }
+    pub fn set_shared_reference(&mut self, reference: Option<Arc<SharedReference>>) -> &mut Self {
+        self.shared_reference = reference;
+        self
+    }
+    pub fn set_colormap(&mut self, colormap: Option<Colormap>) -> &mut Self {
+        self.colormap = colormap;
+        self
+    }
+    pub fn set_normalization(&mut self, normalization: Normalization) -> &mut Self {
+        self.normalization = normalization;
+        self
+    }
+    pub fn set_mode(&mut self, mode: SurfaceMode) -> &mut Self {
+        let cell = &self.cells[0];
+        let surface_width = cell.cell_size * self.width as f64;
+        let center = self.grid_center();
+
+        let channels = match mode {
+            SurfaceMode::Merged => vec![None],
+            SurfaceMode::On => vec![Some(Polarity::On)],
+            SurfaceMode::Off => vec![Some(Polarity::Off)],
+            SurfaceMode::SideBySide => vec![Some(Polarity::On), Some(Polarity::Off)],
+        };
+        // Panels are scaled to share the width of the original grid, with a cell of a panel
+        // between them: `cnt * width + cnt - 1` panel cells fill the `width` grid cells.
+        let cnt = channels.len() as f64;
+        let scale = self.width as f64 / (cnt * self.width as f64 + cnt - 1.0);
+        let gap = cell.cell_size * scale;
+        let panels = channels
+            .into_iter()
+            .enumerate()
+            .map(|(i, channel)| {
+                let panel_center = center
+                    + DVec3::X * (i as f64 - (cnt - 1.0) / 2.0) * (surface_width * scale + gap);
+                Panel {
+                    channel,
+                    scale,
+                    offset: panel_center - center * scale,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        self.mode = mode;
+        self.panels = Arc::new(panels);
+        for idx in 0..self.cells.len() {
+            self.cells
+                .get_mut(idx)
+                .unwrap()
+                .set_panels(self.panels.clone());
+        }
+        self
+    }
+    pub fn mode(&self) -> SurfaceMode {
+        self.mode
+    }
+    /// Show only the events within `window` before now, see [`TimeSurface::set_now`].
+    ///
+    /// Everything drawn for a cell, its square, labels, history, flash, flow arrow and corner
+    /// ring, fades out together.
+    pub fn set_window(&mut self, window: Option<usize>) -> &mut Self {
+        self.window = window;
+        self
+    }
+    pub fn window(&self) -> Option<usize> {
+        self.window
+    }
+    pub fn now(&self) -> usize {
+        self.now
+    }
+    /// Advance the clock without any event, so that the window slides and the flashes fade.
+    ///
+    /// The clock never goes backwards, see [`crate::schedule::FrameScheduler::play_clocked`]
+    /// for running it along the scene time.
+    pub fn set_now(&mut self, now: usize) -> &mut Self {
+        self.now = self.now.max(now);
+        self
+    }
+    /// 1.0 for an event at now, fading linearly to 0.0 at the start of the window.
+    fn window_fade(&self, t: Option<usize>) -> f32 {
+        match (self.window, t) {
+            (None, _) => 1.0,
+            (Some(_), None) => 0.0,
+            (Some(window), Some(t)) => {
+                (1.0 - self.now.saturating_sub(t) as f32 / window.max(1) as f32).max(0.0)
+            }
+        }
+    }
+    /// The window fade of each panel of the cell, empty without a window.
+    fn panel_fades(&self, cell: &TimeSurfaceCell) -> Vec<f32> {
+        match self.window {
+            None => vec![],
+            Some(_) => self
+                .panels
+                .iter()
+                .map(|panel| self.window_fade(cell.channel_t(panel.channel)))
+                .collect(),
+        }
+    }
+    /// Estimate the optical flow with `flow` for every accepted event, see [`crate::flow`].
+    ///
+    /// The arrow of a cell fades out as its event gets older than the `max_age` of the fit.
+    ///
+    /// Changing the fit keeps the flows estimated so far, `None` drops them.
+    pub fn set_flow(&mut self, flow: Option<PlaneFit>) -> &mut Self {
+        match (&mut self.layers.flow, flow) {
+            (Some(layer), Some(fit)) => layer.fit = fit,
+            (layer, fit) => *layer = fit.map(|fit| FlowLayer::new(self.cells.len(), fit)),
+        }
+        self
+    }
+    /// The arrows show how far the edge moves in `flow_horizon` event time, at most 1.5 cells.
+    ///
+    /// Only applies to the flow set with [`TimeSurface::set_flow`], which starts at 100.
+    pub fn set_flow_horizon(&mut self, flow_horizon: usize) -> &mut Self {
+        if let Some(layer) = &mut self.layers.flow {
+            layer.horizon = flow_horizon;
+        }
+        self
+    }
+    /// The flow estimated at the last accepted event of `(x, y)`.
+    pub fn flow_at(&self, x: usize, y: usize) -> Option<Flow> {
+        let idx = self.geometry().index_of(x, y)?;
+        self.layers.flow.as_ref()?.get(idx)
+    }
+    /// Check every accepted event with `detector` against the accepted events of its polarity,
+    /// see [`crate::corner`], and ring the corners.
+    ///
+    /// Changing the detector keeps the corners flagged so far, `None` drops them.
+    pub fn set_corner_detector(&mut self, detector: Option<Arc<dyn CornerDetector>>) -> &mut Self {
+        match (&mut self.layers.corners, detector) {
+            (Some(layer), Some(detector)) => layer.detector = detector,
+            (layer, detector) => {
+                *layer = detector.map(|detector| CornerLayer::new(self.cells.len(), detector))
+            }
+        }
+        self
+    }
+    /// How long the ring of a corner takes to expand and fade out, in event time.
+    ///
+    /// Only applies to the detector set with [`TimeSurface::set_corner_detector`], which
+    /// starts at 100.
+    pub fn set_corner_ring_duration(&mut self, corner_ring_duration: usize) -> &mut Self {
+        if let Some(layer) = &mut self.layers.corners {
+            layer.ring_duration = corner_ring_duration;
+        }
+        self
+    }
+    /// The count of the events flagged as corners
+    pub fn corner_cnt(&self) -> usize {
+        self.layers.corners.as_ref().map_or(0, |layer| layer.cnt)
+    }
+    /// Keep the last `history_len` events of each cell, 0 keeps none, see
+    /// [`CellStyle::history`] for drawing them.
+    ///
+    /// A shorter length drops the oldest events of the histories kept so far.
+    pub fn set_history_len(&mut self, history_len: usize) -> &mut Self {
+        match &mut self.layers.history {
+            _ if history_len == 0 => self.layers.history = None,
+            Some(layer) => layer.set_len(history_len),
+            None => self.layers.history = Some(HistoryLayer::new(self.cells.len(), history_len)),
+        }
+        self
+    }
+    pub fn history_len(&self) -> usize {
+        self.layers.history.as_ref().map_or(0, |layer| layer.len())
+    }
+    /// The last events of `(x, y)`, oldest first.
+    pub fn history(&self, x: usize, y: usize) -> Option<&VecDeque<HistoryEntry>> {
+        const EMPTY: &VecDeque<HistoryEntry> = &VecDeque::new();
+        let idx = self.geometry().index_of(x, y)?;
+        Some(
+            self.layers
+                .history
+                .as_ref()
+                .map_or(EMPTY, |layer| layer.get(idx)),
+        )
+    }
+    pub fn set_flash_duration(&mut self, flash_duration: usize) -> &mut Self {
+        self.flash_duration = flash_duration;
+        self
+    }
+    pub fn set_filter(&mut self, filter: impl EventFilter + 'static) -> &mut Self {
+        self.filter = Arc::new(filter);
+        self
+    }
+    pub fn filter(&self) -> &dyn EventFilter {
+        &*self.filter
+    }
+    pub fn last_decision(&self) -> Option<Decision> {
+        self.last_decision
+    }
+    /// The count of accepted and rejected events
+    pub fn decision_cnts(&self) -> (usize, usize) {
+        (self.accepted_cnt, self.rejected_cnt)
+    }
+    pub fn metrics(&self) -> DenoiseMetrics {
+        self.metrics
+    }
+    /// Feed an event with its ground-truth label, which is scored into the metrics.
+    pub fn accept_labeled(&mut self, labeled: &LabeledEvent) -> Result<Decision, OutOfBounds> {
+        let decision = self.accept(&labeled.event)?;
+        self.metrics.record(decision.accepted, labeled.signal);
+        Ok(decision)
+    }
+    /// Feed an event to the surface, the cell only takes it if the filter accepts it.
+    ///
+    /// An event out of the surface is an error and changes nothing, see [`EventSink::feed`]
+    /// for dropping such events instead. Without a label the decision isn't scored into the
+    /// [`TimeSurface::metrics`], see [`TimeSurface::accept_labeled`].
+    pub fn accept(&mut self, event: &Event) -> Result<Decision, OutOfBounds> {
+        let idx = self.geometry().index(event)?;
+        let (t, x, y, polarity) = (
+            event.t as usize,
+            event.x as usize,
+            event.y as usize,
+            event.polarity,
+        );
+        let (accepted, supporters) = self.filter.decide(&*self, t, x, y);
+        if let Some(layer) = &mut self.layers.history {
+            let entry = HistoryEntry {
+                t,
+                polarity,
+                accepted,
+            };
+            layer.record(idx, entry);
+        }
+        let cell = self.cells.get_mut(idx).unwrap();
+        cell.set_t(t);
+        cell.set_decision(CellDecision {
+            t,
+            polarity,
+            accepted,
+            supporters,
+        });
+        if accepted {
+            cell.accept(t, polarity);
+            self.accepted_cnt += 1;
+        } else {
+            self.rejected_cnt += 1;
+        }
+        if accepted && let Some(layer) = &self.layers.flow {
+            let flow = layer.fit.estimate(&AcceptedTimes(self, polarity), x, y);
+            self.layers.flow.as_mut().unwrap().set(idx, flow);
+        }
+        let corner = accepted
+            && self.layers.corners.as_ref().is_some_and(|layer| {
+                layer
+                    .detector
+                    .is_corner(&AcceptedTimes(self, polarity), x, y)
+            });
+        if corner {
+            self.layers.corners.as_mut().unwrap().record(idx, t);
+        }
+        self.now = self.now.max(t);
+
+        let decision = Decision {
+            t,
+            x,
+            y,
+            accepted,
+            corner,
+        };
+        self.last_decision = Some(decision);
+        Ok(decision)
+    }
+    /// Draw each cell as an isometric box whose height is its normalized value times
+    /// `max_height` cells, `None` draws the flat squares.
+    ///
+    /// The boxes are projected into the plane of the surface, so the default camera shows
+    /// them, and only the boxes are drawn, without the labels, the flashes, the arrows and the
+    /// rings. Play the updates as transforms, like `surface.transform_to(next)`, for the heights
+    /// to grow smoothly.
+    pub fn set_height_field(&mut self, max_height: Option<f64>) -> &mut Self {
+        self.layers.height_field = max_height.map(|max_height| HeightField { max_height });
+        self
+    }
+    /// The values shown, the blended ones in the middle of a transform.
+    fn shown_values(&self) -> Cow<'_, [Vec<f32>]> {
+        match &self.blended_values {
+            Some(values) => Cow::Borrowed(values),
+            None => Cow::Owned(self.values()),
+        }
+    }
+    /// The normalized value of each cell, per panel.
+    fn values(&self) -> Vec<Vec<f32>> {
+        self.panels
+            .iter()
+            .map(|panel| {
+                let ts = self
+                    .cells
+                    .iter()
+                    .map(|cell| cell.channel_t(panel.channel))
+                    .collect::<Vec<_>>();
+                match &self.shared_reference {
+                    Some(reference) => self.normalization.normalize_with(
+                        &ts,
+                        self.now,
+                        reference.get(panel.channel),
+                    ),
+                    None => self.normalization.normalize(&ts, self.now),
+                }
+            })
+            .collect()
+    }
+    /// The primitives of a cell faded by `fades`, which are only cached while nothing of the
+    /// cell is faded, as the fades change with `now`.
+    fn cell_primitives(
+        &self,
+        cell: &CachedExtract<TimeSurfaceCell>,
+        fades: &[f32],
+    ) -> Vec<VItemPrimitive> {
+        if fades.iter().all(|&fade| fade >= 1.0) {
+            return cell.extract();
+        }
+        cell.items(fades)
+            .iter()
+            .map(|item| item.extract())
+            .collect()
+    }
+    /// The fill color of a cell of `panel` with the normalized value `value`.
+    fn cell_color(
+        &self,
+        panel: &Panel,
+        cell: &TimeSurfaceCell,
+        value: f32,
+    ) -> color::AlphaColor<color::Srgb> {
+        match self.colormap {
+            // Diverging maps go from the neutral center towards ON or OFF.
+            Some(colormap) if colormap.is_diverging() => match panel.channel.or(cell.polarity) {
+                Some(Polarity::On) => colormap.sample(0.5 + value / 2.0),
+                Some(Polarity::Off) => colormap.sample(0.5 - value / 2.0),
+                None => colormap.sample(0.5),
+            }
+            .with_alpha(0.7),
+            Some(colormap) => colormap.sample(value).with_alpha(0.7),
+            None => {
+                let (from, to) = cell.channel_ramp(panel.channel);
+                from.lerp_rect(to, value).with_alpha(value * 0.7)
+            }
+        }
+    }
+    /// The flashes of the decisions that are recent enough, faded with the window.
+    fn decision_flashes(&self) -> Vec<VItem> {
+        self.cells
+            .iter()
+            .flat_map(|cell| {
+                let flash = cell.flash_at(self.now, self.flash_duration);
+                cell.flash_items(flash, &self.panel_fades(cell))
+            })
+            .collect()
+    }
+}
+
+/// The accepted event times of a polarity, which the flow of an event of that polarity is
+/// fitted to, as the leading and trailing edges of a moving object have opposite polarities.
+struct AcceptedTimes<'a>(&'a TimeSurface, Polarity);
+
+impl TimestampMap for AcceptedTimes<'_> {
+    fn width(&self) -> usize {
+        self.0.width
+    }
+    fn height(&self) -> usize {
+        self.0.height
+    }
+    fn last_t(&self, x: usize, y: usize) -> Option<usize> {
+        let idx = self.0.geometry().index_of(x, y)?;
+        self.0.cells[idx].channel_t(Some(self.1))
+    }
+}
+
+/// Scale the alpha of the fill and the stroke of `item` by `fade`.
+fn fade_out(item: &mut VItem, fade: f32) {
+    for rgba in item
+        .fill_rgbas
+        .iter_mut()
+        .chain(item.stroke_rgbas.iter_mut())
+    {
+        rgba.0.w *= fade;
+    }
+}
+
+/// A line from `start` to `end` with a triangular tip `tip_size` long.
+fn arrow(
+    start: DVec3,
+    end: DVec3,
+    tip_size: f64,
+    color: color::AlphaColor<color::Srgb>,
+) -> [VItem; 2] {
+    let direction = (end - start).normalize_or_zero();
+    let normal = direction.cross(DVec3::Z);
+    let tip_size = tip_size.min((end - start).length());
+    let base = end - direction * tip_size;
+    let line = VItem::from_vpoints(vec![start, (start + base) / 2.0, base]).with(|line| {
+        line.set_stroke_color(color)
+            .set_stroke_width((tip_size * 0.15) as f32);
+    });
+    let tip = VItem::from(
+        Polygon::new(vec![
+            end,
+            base + normal * tip_size * 0.5,
+            base - normal * tip_size * 0.5,
+        ])
+        .with(|tip| {
+            tip.set_fill_color(color)
+                .set_stroke_color(color.with_alpha(0.0));
+        }),
+    );
+    [line, tip]
+}
+
+impl BoundingBox for TimeSurface {
+    /// The box around the panels.
+    fn get_bounding_box(&self) -> [DVec3; 3] {
+        let half = dvec3(self.width as f64, self.height as f64, 0.0) * self.cell_size() / 2.0;
+        let center = self.grid_center();
+        let corners = self
+            .panels
+            .iter()
+            .flat_map(|panel| {
+                [center - half, center + half].map(|corner| corner * panel.scale + panel.offset)
+            })
+            .collect::<Vec<_>>();
+        corners.get_bounding_box()
+    }
+}
+
+impl Shift for TimeSurface {
+    fn shift(&mut self, shift: DVec3) -> &mut Self {
+        let start = self.cells[0].start + shift;
+        self.set_layout(start, self.cell_size())
+    }
+}
+
+impl Scale for TimeSurface {
+    /// See [`square_scale`].
+    fn scale_by_anchor(&mut self, scale: DVec3, anchor: Anchor) -> &mut Self {
+        let (start, factor) = square_scale(self, self.cells[0].start, scale, anchor);
+        self.set_layout(start, self.cell_size() * factor)
+    }
+}
+
+/// Builds a [`TimeSurface`] with its placement and style.
+#[derive(Debug, Clone)]
+pub struct TimeSurfaceBuilder {
+    width: usize,
+    height: usize,
+    /// `(center, width, height)`
+    rect: Option<(DVec3, f64, f64)>,
+    style: CellStyle,
+    history_len: usize,
+}
+
+impl TimeSurfaceBuilder {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            rect: None,
+            style: CellStyle::default(),
+            history_len: 0,
+        }
+    }
+    /// See [`TimeSurface::fit_in`].
+    pub fn with_rect(mut self, center: DVec3, width: f64, height: f64) -> Self {
+        self.rect = Some((center, width, height));
+        self
+    }
+    pub fn with_padding(mut self, padding_ratio: f64) -> Self {
+        self.style.padding_ratio = padding_ratio;
+        self
+    }
+    pub fn with_corner_radius(mut self, corner_radius: f64) -> Self {
+        self.style.corner_radius = corner_radius;
+        self
+    }
+    pub fn with_labels(mut self, show_labels: bool) -> Self {
+        self.style.show_labels = show_labels;
+        self
+    }
+    pub fn with_label_scale(mut self, label_scale: f64) -> Self {
+        self.style.label_scale = label_scale;
+        self
+    }
+    /// Keep the last `len` events of each cell and draw them with `style`.
+    pub fn with_history(mut self, len: usize, style: HistoryStyle) -> Self {
+        self.history_len = len;
+        self.style.history = Some(style);
+        self
+    }
+    pub fn build(self) -> TimeSurface {
+        let mut surface = TimeSurface::new(self.width, self.height);
+        surface
+            .set_style(self.style)
+            .set_history_len(self.history_len);
+        if let Some((center, width, height)) = self.rect {
+            surface.fit_in(center, width, height);
+        }
+        surface
+    }
+}
+
+impl EventSink for TimeSurface {
+    fn geometry(&self) -> SensorGeometry {
+        TimeSurface::geometry(self)
+    }
+    fn accept(&mut self, event: &Event) -> Result<(), OutOfBounds> {
+        TimeSurface::accept(self, event).map(|_| ())
+    }
+}
+
+impl TimestampMap for TimeSurface {
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn last_t(&self, x: usize, y: usize) -> Option<usize> {
+        self.cells[self.geometry().index_of(x, y)?].t
+    }
+}
+
+impl Extract for TimeSurface {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let values = self.shown_values();
+        if let Some(height_field) = &self.layers.height_field {
+            return height_field
+                .boxes(self, &values)
+                .into_iter()
+                .map(|item| item.extract())
+                .collect();
+        }
+        let mut primitives = self
+            .cells
+            .par_iter() // Without par: 207724.5 µs, With par:
+            .enumerate()
+            .flat_map(|(cell_idx, cell)| {
+                let fades = self.panel_fades(cell);
+                self.cell_primitives(cell, &fades).with(|primitive| {
+                    for (idx, (panel, values)) in self.panels.iter().zip(values.iter()).enumerate()
+                    {
+                        let color = self.cell_color(panel, cell, values[cell_idx]);
+                        let fade = fades.get(idx).copied().unwrap_or(1.0);
+                        primitive[idx].set_fill_color(color.with_alpha(color.components[3] * fade));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let layers = &self.layers;
+        primitives.extend(
+            (layers.history.iter().flat_map(|layer| layer.items(self)))
+                .chain(self.decision_flashes())
+                .chain(layers.flow.iter().flat_map(|layer| layer.arrows(self)))
+                .chain(layers.corners.iter().flat_map(|layer| layer.rings(self)))
+                .map(|item| item.extract()),
+        );
+        primitives
+    }
+}
+
+impl Alignable for TimeSurface {
+    /// The values of surfaces of the same size with the same channels can be blended.
+    fn is_aligned(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self
+                .panels
+                .iter()
+                .map(|panel| panel.channel)
+                .eq(other.panels.iter().map(|panel| panel.channel))
+    }
+    fn align_with(&mut self, _other: &mut Self) {}
+}
+
+impl Interpolatable for TimeSurface {
+    /// The target with the normalized values of the cells blended, so that the colors and the
+    /// heights of [`TimeSurface::set_height_field`] change smoothly in a transform.
+    ///
+    /// The rest, like the labels and the flashes, is the target's, and surfaces that aren't
+    /// aligned, see [`Alignable::is_aligned`], aren't blended.
+    fn lerp(&self, target: &Self, t: f64) -> Self {
+        if t >= 1.0 || !self.is_aligned(target) {
+            return target.clone();
+        }
+        let values = self
+            .shown_values()
+            .iter()
+            .zip(target.values())
+            .map(|(from, to)| {
+                from.iter()
+                    .zip(to)
+                    .map(|(from, to)| from.lerp(&to, t))
+                    .collect()
+            })
+            .collect();
+        let mut surface = target.clone();
+        surface.blended_values = Some(Arc::new(values));
+        surface
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        corner::{ArcStar, EFast},
+        filter::BackgroundActivityFilter,
+        generator::{BackgroundActivity, Generator, MovingBar},
+    };
+
+    const MODES: [SurfaceMode; 4] = [
+        SurfaceMode::Merged,
+        SurfaceMode::On,
+        SurfaceMode::Off,
+        SurfaceMode::SideBySide,
+    ];
+
+    fn assert_inside(surface: &TimeSurface, center: DVec3, width: f64, height: f64) {
+        let [min, _, max] = surface.get_bounding_box();
+        let half = dvec3(width, height, 0.0) / 2.0;
+        assert!(
+            (min - (center - half)).min_element() > -1e-9
+                && ((center + half) - max).min_element() > -1e-9,
+            "{:?}: {min} .. {max} is outside of {center} +- {half}",
+            surface.mode()
+        );
+    }
+
+    /// A surface with `filter` fed with `events`.
+    fn fed(
+        width: usize,
+        height: usize,
+        filter: impl EventFilter + 'static,
+        events: &[LabeledEvent],
+    ) -> TimeSurface {
+        let mut surface = TimeSurface::new(width, height);
+        surface.set_filter(filter);
+        for event in events {
+            surface.accept_labeled(event).unwrap();
+        }
+        surface
+    }
+
+    #[test]
+    fn generated_streams_give_the_same_surface() {
+        let (width, height) = (16, 12);
+        let events = (
+            MovingBar::new(2.0, 20.0).with_step(5),
+            BackgroundActivity::new(0.5),
+        )
+            .generate(3, width, height, 2000);
+        let surface = fed(width, height, BackgroundActivityFilter::new(100), &events);
+        let again = fed(width, height, BackgroundActivityFilter::new(100), &events);
+        assert_eq!(surface.metrics(), again.metrics());
+        assert_eq!(surface.decision_cnts(), again.decision_cnts());
+        assert_eq!(surface.channel_times(None), again.channel_times(None));
+        assert_eq!(surface.metrics().total(), events.len());
+
+        // The filter keeps nearly all of the bar and drops most of the noise.
+        let raw = fed(width, height, PassThrough, &events).metrics();
+        let filtered = surface.metrics();
+        assert!(filtered.true_negative > filtered.false_positive);
+        assert!(filtered.precision().unwrap() > raw.precision().unwrap() + 0.05);
+        assert!(filtered.recall().unwrap() > 0.95);
+    }
+
+    #[test]
+    fn only_labeled_events_are_scored() {
+        let mut surface = TimeSurface::new(4, 4);
+        surface.set_filter(BackgroundActivityFilter::new(10));
+        surface.accept(&Event::new(0, 1, 1, Polarity::On)).unwrap();
+        assert_eq!(surface.metrics().total(), 0);
+
+        let labeled = |t, x, signal| LabeledEvent {
+            event: Event::new(t, x, 1, Polarity::On),
+            signal,
+        };
+        // Supported by the event before, then alone.
+        surface.accept_labeled(&labeled(5, 2, true)).unwrap();
+        surface.accept_labeled(&labeled(100, 0, true)).unwrap();
+        surface.accept_labeled(&labeled(200, 3, false)).unwrap();
+        assert_eq!(
+            surface.metrics(),
+            DenoiseMetrics {
+                true_positive: 1,
+                false_positive: 0,
+                false_negative: 1,
+                true_negative: 1,
+            }
+        );
+        assert_eq!(surface.decision_cnts(), (1, 3));
+    }
+
+    #[test]
+    fn panels_stay_in_the_fitted_rect() {
+        let (center, width, height) = (dvec3(1.0, -2.0, 0.0), 6.0, 4.0);
+        for (grid_width, grid_height) in [(1, 1), (4, 4), (12, 5), (3, 9)] {
+            for mode in MODES {
+                let mut surface = TimeSurface::new(grid_width, grid_height);
+                surface.set_mode(mode).fit_in(center, width, height);
+                assert_inside(&surface, center, width, height);
+
+                // The mode set after the layout keeps it inside as well.
+                let mut surface = TimeSurface::builder(grid_width, grid_height)
+                    .with_rect(center, width, height)
+                    .build();
+                surface.set_mode(mode);
+                assert_inside(&surface, center, width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn side_by_side_panels_fill_the_width() {
+        let mut surface = TimeSurface::new(4, 4);
+        surface
+            .set_mode(SurfaceMode::SideBySide)
+            .fit_in(DVec3::ZERO, 9.0, 9.0);
+        let [min, _, max] = surface.get_bounding_box();
+        assert!((max.x - min.x - 9.0).abs() < 1e-9);
+        // Two panels of four cells and a cell between them.
+        assert!((max.y - min.y - 4.0).abs() < 1e-9);
+        let (on, on_size) = surface.cell_rect(3, 0, Polarity::On).unwrap();
+        let (off, off_size) = surface.cell_rect(0, 0, Polarity::Off).unwrap();
+        assert_eq!((on_size, off_size), (1.0, 1.0));
+        assert!((off.x - on.x - 2.0).abs() < 1e-9);
+    }
+
+    fn on(t: u64, x: u16, y: u16) -> Event {
+        Event::new(t, x, y, Polarity::On)
+    }
+
+    #[test]
+    fn decision_flash_fades_out() {
+        let mut surface = TimeSurface::new(4, 4);
+        surface
+            .set_filter(BackgroundActivityFilter::new(10))
+            .set_flash_duration(50);
+        surface.accept(&on(100, 1, 1)).unwrap();
+        surface.accept(&on(105, 2, 1)).unwrap();
+        let flash = |surface: &TimeSurface, idx: usize| {
+            surface.cells[idx].flash_at(surface.now, surface.flash_duration)
+        };
+        let (first, second) = (5, 6);
+        assert_eq!(flash(&surface, first), 0.9);
+        assert_eq!(flash(&surface, second), 1.0);
+        // The second event is supported by the first one, which was alone.
+        let decision = surface.cells[second].decision.clone().unwrap();
+        assert!(decision.accepted);
+        assert_eq!(decision.supporters, vec![(1, 1)]);
+        assert!(!surface.cells[first].decision.as_ref().unwrap().accepted);
+
+        surface.set_now(125);
+        assert_eq!(flash(&surface, first), 0.5);
+        assert_eq!(flash(&surface, second), 0.6);
+        surface.set_now(150);
+        assert_eq!(flash(&surface, first), 0.0);
+        assert!(flash(&surface, second) > 0.0);
+        surface.set_now(155);
+        assert_eq!(flash(&surface, second), 0.0);
+    }
+
+    #[test]
+    fn flash_overlay_is_drawn_while_flashing() {
+        let mut surface = TimeSurface::builder(4, 4).with_labels(false).build();
+        surface
+            .set_filter(BackgroundActivityFilter::new(10))
+            .set_flash_duration(50);
+        surface.accept(&on(100, 1, 1)).unwrap();
+        surface.accept(&on(105, 2, 1)).unwrap();
+        // The flash, then an outline and a link per supporter.
+        assert_eq!(surface.cells[5].flash_items(1.0, &[]).len(), 1);
+        assert_eq!(surface.cells[6].flash_items(1.0, &[]).len(), 3);
+        assert_eq!(surface.decision_flashes().len(), 4);
+        // The cells themselves only draw their squares.
+        assert_eq!(surface.cells[6].extract().len(), 1);
+        let cells = surface.cells.clone();
+        surface.set_now(200);
+        assert!(surface.decision_flashes().is_empty());
+        assert!(std::ptr::eq(&*surface.cells[6], &*cells[6]));
+    }
+
+    #[test]
+    fn flashing_cells_are_the_recent_decisions() {
+        let (width, height) = (16, 12);
+        let events = (
+            MovingBar::new(2.0, 20.0).with_step(5),
+            BackgroundActivity::new(0.5),
+        )
+            .generate(5, width, height, 1000);
+        let mut surface = TimeSurface::new(width, height);
+        surface
+            .set_filter(BackgroundActivityFilter::new(100))
+            .set_flash_duration(80);
+        for (i, event) in events.iter().enumerate() {
+            surface.accept(&event.event).unwrap();
+            if i % 50 != 0 {
+                continue;
+            }
+            // The flash, then an outline and a link per supporter, for every recent decision.
+            let flashes = surface
+                .cells
+                .iter()
+                .filter_map(|cell| cell.decision.as_ref())
+                .filter(|decision| surface.now - decision.t < 80)
+                .map(|decision| 1 + decision.supporters.len() * 2)
+                .sum::<usize>();
+            assert_eq!(surface.decision_flashes().len(), flashes);
+        }
+    }
+
+    fn off(t: u64, x: u16, y: u16) -> Event {
+        Event::new(t, x, y, Polarity::Off)
+    }
+
+    /// The alpha of the first fill of the label primitives of `cell`, which come after its
+    /// squares.
+    /// The alpha of the label of the first panel of a cell.
+    fn label_alpha(surface: &TimeSurface, idx: usize) -> f32 {
+        let cell = &surface.cells[idx];
+        let primitives = surface.cell_primitives(cell, &surface.panel_fades(cell));
+        let label = &primitives[cell.panels.len()];
+        label.fill_color().components[3]
+    }
+
+    #[test]
+    fn window_fades_the_cells_out() {
+        let mut surface = TimeSurface::new(4, 4);
+        surface.set_window(Some(100));
+        assert!(
+            surface
+                .cells
+                .iter()
+                .all(|cell| surface.panel_fades(cell) == [0.0])
+        );
+        surface.accept(&on(0, 0, 0)).unwrap();
+        surface.accept(&on(50, 1, 0)).unwrap();
+        assert_eq!(label_alpha(&surface, 0), 0.5);
+        assert_eq!(label_alpha(&surface, 1), 1.0);
+
+        surface.set_now(100);
+        assert_eq!(label_alpha(&surface, 0), 0.0);
+        assert_eq!(label_alpha(&surface, 1), 0.5);
+
+        // Without a window nothing fades.
+        surface.set_window(None);
+        assert!(
+            surface
+                .cells
+                .iter()
+                .all(|cell| surface.panel_fades(cell).is_empty())
+        );
+        assert_eq!(label_alpha(&surface, 0), 1.0);
+    }
+
+    #[test]
+    fn window_fades_without_touching_the_cells() {
+        let mut surface = TimeSurface::new(4, 4);
+        surface.set_window(Some(100));
+        surface.accept(&on(0, 0, 0)).unwrap();
+        surface.cells[0].extract();
+        let cells = surface.cells.clone();
+        surface.set_now(50);
+        assert!(surface.cells[0].is_cached());
+        assert!(
+            surface
+                .cells
+                .iter()
+                .zip(cells.iter())
+                .all(|(cell, before)| std::ptr::eq(&**cell, &**before))
+        );
+        assert_eq!(label_alpha(&surface, 0), 0.5);
+    }
+
+    #[test]
+    fn window_fades_each_panel_by_its_channel() {
+        let mut surface = TimeSurface::new(4, 4);
+        surface
+            .set_mode(SurfaceMode::SideBySide)
+            .set_window(Some(128));
+        surface.accept(&on(0, 2, 2)).unwrap();
+        surface.accept(&off(64, 2, 2)).unwrap();
+        surface.set_now(96);
+        assert_eq!(surface.panel_fades(&surface.cells[10]), [0.25, 0.75]);
+        // Merging the panels fades by the last event.
+        surface.set_mode(SurfaceMode::Merged);
+        assert_eq!(surface.panel_fades(&surface.cells[10]), [0.75]);
+        assert_eq!(label_alpha(&surface, 10), 0.75);
+    }
+
+    #[test]
+    fn window_fade_follows_the_generated_stream() {
+        let (width, height, window) = (16, 12, 150);
+        let events = MovingBar::new(2.0, 20.0)
+            .with_step(5)
+            .generate(5, width, height, 1000);
+        let mut surface = TimeSurface::new(width, height);
+        surface.set_window(Some(window));
+        for chunk in events.chunks(40) {
+            for event in chunk {
+                surface.accept(&event.event).unwrap();
+            }
+            for (idx, cell) in surface.cells.iter().enumerate() {
+                let fade = surface.window_fade(cell.channel_t(None));
+                assert_eq!(label_alpha(&surface, idx), fade, "cell {idx}");
+            }
+        }
+    }
+
+    #[test]
+    fn window_fades_the_arrows_and_rings() {
+        let mut surface = TimeSurface::new(4, 4);
+        surface
+            .set_flow(Some(PlaneFit::new(1, 256)))
+            .set_corner_detector(Some(Arc::new(ArcStar)))
+            .set_corner_ring_duration(256)
+            .set_window(Some(128));
+        surface.accept(&on(0, 1, 1)).unwrap();
+        let layers = &mut surface.layers;
+        let flow = Some(Flow { vx: 0.01, vy: 0.0 });
+        layers.flow.as_mut().unwrap().set(5, flow);
+        layers.corners.as_mut().unwrap().record(5, 0);
+        let items = |surface: &TimeSurface| {
+            let arrows = surface.layers.flow.as_ref().unwrap().arrows(surface);
+            let rings = surface.layers.corners.as_ref().unwrap().rings(surface);
+            (arrows, rings)
+        };
+        let alphas = |surface: &TimeSurface| {
+            let (arrows, rings) = items(surface);
+            (arrows[0].stroke_rgbas[0].0.w, rings[0].stroke_rgbas[0].0.w)
+        };
+        assert_eq!(alphas(&surface), (1.0, 1.0));
+        // A quarter of the age and half of the window have passed.
+        surface.set_now(64);
+        assert_eq!(alphas(&surface), (0.375, 0.375));
+        surface.set_now(128);
+        let (arrows, rings) = items(&surface);
+        assert!(arrows.is_empty() && rings.is_empty());
+        // Without a window only the age fades them.
+        surface.set_window(None);
+        assert_eq!(alphas(&surface), (0.5, 0.5));
+    }
+
+    #[test]
+    fn layers_keep_their_state_until_disabled() {
+        let mut surface = TimeSurface::new(4, 4);
+        assert!(surface.layers.flow.is_none() && surface.layers.corners.is_none());
+        surface
+            .set_flow(Some(PlaneFit::new(1, 256)))
+            .set_flow_horizon(50)
+            .set_corner_detector(Some(Arc::new(ArcStar)));
+        surface
+            .layers
+            .flow
+            .as_mut()
+            .unwrap()
+            .set(5, Some(Flow { vx: 0.01, vy: 0.0 }));
+        surface.layers.corners.as_mut().unwrap().record(5, 0);
+
+        // A new fit or detector keeps the state and the settings.
+        surface
+            .set_flow(Some(PlaneFit::new(2, 100)))
+            .set_corner_detector(Some(Arc::new(EFast)));
+        assert_eq!(surface.flow_at(1, 1), Some(Flow { vx: 0.01, vy: 0.0 }));
+        assert_eq!(surface.layers.flow.as_ref().unwrap().horizon, 50);
+        assert_eq!(surface.corner_cnt(), 1);
+
+        surface.set_flow(None).set_corner_detector(None);
+        assert_eq!(surface.flow_at(1, 1), None);
+        assert_eq!(surface.corner_cnt(), 0);
+        surface.set_flow(Some(PlaneFit::new(1, 256)));
+        assert_eq!(surface.flow_at(1, 1), None);
+    }
+
+    #[test]
+    fn history_keeps_the_last_events() {
+        let mut surface = TimeSurface::new(4, 4);
+        surface.set_filter(BackgroundActivityFilter::new(10));
+        // Nothing is recorded without a length.
+        surface.accept(&on(0, 1, 1)).unwrap();
+        assert!(surface.history(1, 1).unwrap().is_empty());
+
+        surface.set_history_len(3);
+        surface.accept(&on(5, 2, 1)).unwrap();
+        for event in [on(10, 1, 1), off(30, 1, 1), on(35, 1, 1), off(50, 1, 1)] {
+            surface.accept(&event).unwrap();
+        }
+        let entry = |t, polarity, accepted| HistoryEntry {
+            t,
+            polarity,
+            accepted,
+        };
+        // The event at 10 is supported by the one at 5 next to it, the later ones only by
+        // their own pixel, which the filter doesn't count.
+        assert_eq!(
+            surface.history(1, 1).unwrap(),
+            &[
+                entry(30, Polarity::Off, false),
+                entry(35, Polarity::On, false),
+                entry(50, Polarity::Off, false),
+            ]
+        );
+        assert_eq!(
+            surface.history(2, 1).unwrap(),
+            &[entry(5, Polarity::On, true)]
+        );
+        assert_eq!(surface.history(4, 1), None);
+
+        // A shorter length drops the oldest entries, a longer one keeps them.
+        surface.set_history_len(2);
+        assert_eq!(
+            surface.history(1, 1).unwrap(),
+            &[
+                entry(35, Polarity::On, false),
+                entry(50, Polarity::Off, false)
+            ]
+        );
+        surface.set_history_len(4);
+        assert_eq!(surface.history(1, 1).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn history_is_bounded_on_a_generated_stream() {
+        let (width, height, len) = (16, 12, 4);
+        let events = (
+            MovingBar::new(2.0, 40.0).with_step(5),
+            BackgroundActivity::new(2.0),
+        )
+            .generate(5, width, height, 1000);
+        let mut surface = TimeSurface::builder(width, height)
+            .with_history(len, HistoryStyle::SpikeTrain { span: 200 })
+            .build();
+        surface.set_filter(BackgroundActivityFilter::new(50));
+        let mut decisions = vec![vec![]; width * height];
+        for labeled in &events {
+            let decision = surface.accept(&labeled.event).unwrap();
+            decisions[decision.y * width + decision.x].push(HistoryEntry {
+                t: decision.t,
+                polarity: labeled.event.polarity,
+                accepted: decision.accepted,
+            });
+        }
+        assert!(decisions.iter().any(|cell| cell.len() > len));
+        for (idx, decisions) in decisions.iter().enumerate() {
+            let history = surface.history(idx % width, idx / width).unwrap();
+            let kept = &decisions[decisions.len().saturating_sub(len)..];
+            assert!(history.iter().eq(kept), "cell {idx}");
+        }
+    }
+
+    #[test]
+    fn history_is_drawn_per_panel() {
+        let mut surface = TimeSurface::builder(4, 4)
+            .with_labels(false)
+            .with_history(8, HistoryStyle::SpikeTrain { span: 100 })
+            .build();
+        surface.set_flash_duration(1);
+        for event in [on(0, 1, 1), off(60, 1, 1), on(150, 1, 1)] {
+            surface.accept(&event).unwrap();
+        }
+        surface.set_now(200);
+        let drawn = |surface: &TimeSurface| {
+            let layer = surface.layers.history.as_ref().unwrap();
+            layer.cell_items(5, &surface.cells[5], &[]).len()
+        };
+        // The two spikes within the span of the newest event, over the square.
+        assert_eq!(drawn(&surface), 2);
+        assert_eq!(surface.extract().len(), 16 + 2);
+        // Each panel shows the spikes of its channel within the span of its newest event.
+        surface.set_mode(SurfaceMode::SideBySide);
+        assert_eq!(drawn(&surface), 1 + 1);
+
+        let mut style = surface.style();
+        style.history = Some(HistoryStyle::Sparkline { max_interval: 100 });
+        surface.set_mode(SurfaceMode::Merged).set_style(style);
+        // A line through the two intervals.
+        assert_eq!(drawn(&surface), 1);
+    }
+
+    #[test]
+    fn lerp_blends_the_normalized_values() {
+        let mut from = TimeSurface::new(4, 4);
+        from.set_normalization(Normalization::Linear { window: Some(100) });
+        from.accept(&on(1000, 0, 0)).unwrap();
+        from.accept(&on(1050, 1, 0)).unwrap();
+        let mut to = from.clone();
+        to.accept(&off(1100, 2, 0)).unwrap();
+        assert_eq!(&from.values()[0][..3], [0.5, 1.0, 0.0]);
+        assert_eq!(&to.values()[0][..3], [0.0, 0.5, 1.0]);
+
+        let mid = from.lerp(&to, 0.5);
+        assert_eq!(
+            &mid.blended_values.as_ref().unwrap()[0][..3],
+            [0.25, 0.75, 0.5]
+        );
+        // The rest is the target's.
+        assert_eq!(mid.now(), 1100);
+        assert_eq!(mid.last_t(2, 0), Some(1100));
+        // A transform goes on from the blended values.
+        let later = mid.lerp(&to, 0.5);
+        assert_eq!(
+            &later.blended_values.as_ref().unwrap()[0][..3],
+            [0.125, 0.625, 0.75]
+        );
+        assert!(from.lerp(&to, 1.0).blended_values.is_none());
+        assert!(later.lerp(&to, 1.0).blended_values.is_none());
+
+        // Surfaces with different panels aren't blended.
+        to.set_mode(SurfaceMode::SideBySide);
+        assert!(from.lerp(&to, 0.5).blended_values.is_none());
+        from.set_mode(SurfaceMode::On);
+        to.set_mode(SurfaceMode::Off);
+        assert!(!from.is_aligned(&to));
+        assert!(from.lerp(&to, 0.5).blended_values.is_none());
+    }
+
+    #[test]
+    fn transform_between_sizes_jumps_to_the_target() {
+        let mut from = TimeSurface::new(4, 4);
+        from.accept(&on(10, 3, 3)).unwrap();
+        let mut to = TimeSurface::new(6, 3);
+        to.accept(&on(20, 5, 2)).unwrap();
+        for (from, to) in [(&from, &to), (&to, &from)] {
+            assert!(!from.is_aligned(to));
+            let mid = from.lerp(to, 0.5);
+            assert!(mid.blended_values.is_none());
+            assert_eq!((mid.width, mid.height), (to.width, to.height));
+            assert_eq!(mid.extract().len(), to.extract().len());
+        }
+    }
+
+    #[test]
+    fn height_field_draws_a_box_per_filled_cell() {
+        let mut surface = TimeSurface::builder(4, 4).with_labels(false).build();
+        surface.set_normalization(Normalization::Linear { window: Some(100) });
+        surface.accept(&on(1000, 0, 0)).unwrap();
+        surface.accept(&on(1050, 1, 0)).unwrap();
+        // The squares and a flash for the last event.
+        assert_eq!(surface.extract().len(), 16 + 1);
+        surface.set_height_field(Some(2.0));
+        // A top and two sides for each filled cell and a floor for the 14 others.
+        assert_eq!(surface.extract().len(), 2 * 3 + 14);
+        // A cell that has faded out of the window is back to the floor.
+        surface.set_window(Some(60)).set_now(1060);
+        assert_eq!(surface.extract().len(), 3 + 15);
+
+        // The tallest box rises `max_height` cells above its floor.
+        surface.set_window(None);
+        let boxes = HeightField { max_height: 2.0 }.boxes(&surface, &surface.values());
+        let [min, _, max] = boxes
+            .iter()
+            .flat_map(|item| item.get_bounding_box())
+            .collect::<Vec<_>>()
+            .get_bounding_box();
+        assert!(max.y - min.y > 2.0 * surface.cell_size());
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one cell, got 0x4")]
+    fn empty_surfaces_are_rejected() {
+        TimeSurface::new(0, 4);
+    }
+}
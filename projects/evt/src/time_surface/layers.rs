@@ -0,0 +1,405 @@
+//! The optional layers of a [`TimeSurface`].
+//!
+//! Each layer keeps its own state per cell next to the cells instead of in them, so a surface
+//! without the layer carries none of it, and draws itself over the cells or, for the height
+//! field, instead of them.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use itertools::Itertools;
+use ranim::{
+    color::palettes::manim,
+    glam::{DVec3, dvec3},
+    items::vitem::{
+        VItem,
+        geometry::{Circle, Polygon},
+    },
+    prelude::*,
+};
+
+use super::{HistoryEntry, HistoryStyle, Panel, TimeSurface, TimeSurfaceCell, arrow, fade_out};
+use crate::{
+    corner::CornerDetector,
+    event::Polarity,
+    flow::{Flow, PlaneFit},
+    persistent::PersistentVec,
+};
+
+/// The optional layers of a surface, `None` when disabled.
+#[derive(Clone, Default)]
+pub(super) struct Layers {
+    pub history: Option<HistoryLayer>,
+    pub flow: Option<FlowLayer>,
+    pub corners: Option<CornerLayer>,
+    pub height_field: Option<HeightField>,
+}
+
+/// A value for each of `len` cells.
+fn per_cell<T: Clone>(len: usize, value: T) -> PersistentVec<T> {
+    (0..len).map(|_| value.clone()).collect()
+}
+
+/// The last events of each cell, see [`TimeSurface::set_history_len`].
+#[derive(Clone)]
+pub(super) struct HistoryLayer {
+    len: usize,
+    /// Oldest first
+    entries: PersistentVec<VecDeque<HistoryEntry>>,
+}
+
+impl HistoryLayer {
+    pub fn new(cell_cnt: usize, len: usize) -> Self {
+        Self {
+            len,
+            entries: per_cell(cell_cnt, VecDeque::new()),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Drop the oldest events of the cells beyond `len`.
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        for idx in 0..self.entries.len() {
+            if self.entries[idx].len() > len {
+                let entries = self.entries.get_mut(idx).unwrap();
+                entries.drain(..entries.len() - len);
+            }
+        }
+    }
+    pub fn get(&self, idx: usize) -> &VecDeque<HistoryEntry> {
+        &self.entries[idx]
+    }
+    /// Push an event to the history of a cell, dropping the oldest ones beyond `len`.
+    pub fn record(&mut self, idx: usize, entry: HistoryEntry) {
+        let entries = self.entries.get_mut(idx).unwrap();
+        entries.push_back(entry);
+        if entries.len() > self.len {
+            entries.pop_front();
+        }
+    }
+    /// The history of every cell in each panel, faded with the window.
+    pub fn items(&self, surface: &TimeSurface) -> Vec<VItem> {
+        surface
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, cell)| self.cell_items(idx, cell, &surface.panel_fades(cell)))
+            .collect()
+    }
+    /// The history of a cell in each panel, the ones of the `idx`-th panel faded by
+    /// `fades[idx]`.
+    pub fn cell_items(&self, idx: usize, cell: &TimeSurfaceCell, fades: &[f32]) -> Vec<VItem> {
+        let square_size = cell.cell_size * (1.0 - cell.style.padding_ratio);
+        cell.panels
+            .iter()
+            .enumerate()
+            .flat_map(|(panel_idx, panel)| {
+                let fade = fades.get(panel_idx).copied().unwrap_or(1.0);
+                self.panel_items(idx, cell, panel, square_size * panel.scale)
+                    .into_iter()
+                    .map(move |mut item| {
+                        fade_out(&mut item, fade);
+                        item
+                    })
+            })
+            .collect()
+    }
+    /// The history of the events of a cell shown by `panel`, drawn in the lower part of a
+    /// square of `size`.
+    fn panel_items(
+        &self,
+        idx: usize,
+        cell: &TimeSurfaceCell,
+        panel: &Panel,
+        size: f64,
+    ) -> Vec<VItem> {
+        let Some(style) = cell.style.history else {
+            return vec![];
+        };
+        let entries = self.entries[idx]
+            .iter()
+            .filter(|entry| {
+                panel
+                    .channel
+                    .is_none_or(|channel| channel == entry.polarity)
+            })
+            .collect::<Vec<_>>();
+        let Some(newest) = entries.last() else {
+            return vec![];
+        };
+        // A strip over the lower third of the square, below the label.
+        let center = cell.panel_center_of(panel, cell.x, cell.y);
+        let (left, width) = (center.x - size * 0.4, size * 0.8);
+        let (mid, half_height) = (center.y - size * 0.3, size * 0.13);
+        let stroke_width = (size * 0.04) as f32;
+        let line = |from: DVec3, to: DVec3, color: color::AlphaColor<color::Srgb>| {
+            VItem::from_vpoints(vec![from, (from + to) / 2.0, to]).with(|line| {
+                line.set_stroke_color(color).set_stroke_width(stroke_width);
+            })
+        };
+        match style {
+            HistoryStyle::SpikeTrain { span } => entries
+                .iter()
+                .filter_map(|entry| {
+                    let age = newest.t.saturating_sub(entry.t) as f64 / span.max(1) as f64;
+                    (age <= 1.0).then_some((entry, left + width * (1.0 - age)))
+                })
+                .map(|(entry, x)| {
+                    let color = if !entry.accepted {
+                        manim::RED_C
+                    } else {
+                        cell.channel_ramp(Some(entry.polarity)).1
+                    };
+                    let tip = match entry.polarity {
+                        Polarity::On => mid + half_height,
+                        Polarity::Off => mid - half_height,
+                    };
+                    line(dvec3(x, mid, center.z), dvec3(x, tip, center.z), color)
+                })
+                .collect(),
+            HistoryStyle::Sparkline { max_interval } => {
+                if entries.len() < 2 {
+                    return vec![];
+                }
+                let step = width / (entries.len() - 2).max(1) as f64;
+                let points = entries
+                    .iter()
+                    .tuple_windows()
+                    .enumerate()
+                    .map(|(i, (prev, entry))| {
+                        let interval = entry.t.saturating_sub(prev.t) as f64;
+                        let closeness = 1.0 - (interval / max_interval.max(1) as f64).min(1.0);
+                        let x = if entries.len() == 2 {
+                            center.x
+                        } else {
+                            left + i as f64 * step
+                        };
+                        dvec3(
+                            x,
+                            mid - half_height + 2.0 * half_height * closeness,
+                            center.z,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                if let [point] = points[..] {
+                    return vec![line(
+                        point - DVec3::X * size * 0.05,
+                        point + DVec3::X * size * 0.05,
+                        manim::WHITE,
+                    )];
+                }
+                let mut vpoints = vec![points[0]];
+                for (from, to) in points.iter().tuple_windows() {
+                    vpoints.extend([(from + to) / 2.0, *to]);
+                }
+                vec![VItem::from_vpoints(vpoints).with(|line| {
+                    line.set_stroke_color(manim::WHITE)
+                        .set_stroke_width(stroke_width);
+                })]
+            }
+        }
+    }
+}
+
+/// The flow of the last accepted event of each cell, see [`TimeSurface::set_flow`].
+#[derive(Clone)]
+pub(super) struct FlowLayer {
+    pub fit: PlaneFit,
+    /// The arrows show the displacement over this much event time
+    pub horizon: usize,
+    flows: PersistentVec<Option<Flow>>,
+}
+
+impl FlowLayer {
+    pub fn new(cell_cnt: usize, fit: PlaneFit) -> Self {
+        Self {
+            fit,
+            horizon: 100,
+            flows: per_cell(cell_cnt, None),
+        }
+    }
+    pub fn get(&self, idx: usize) -> Option<Flow> {
+        self.flows[idx]
+    }
+    pub fn set(&mut self, idx: usize, flow: Option<Flow>) {
+        *self.flows.get_mut(idx).unwrap() = flow;
+    }
+    /// The arrows of the cells whose event is recent enough, over the panels that show it,
+    /// faded with the window.
+    pub fn arrows(&self, surface: &TimeSurface) -> Vec<VItem> {
+        let cell_size = surface.cell_size();
+        let mut arrows = vec![];
+        for (cell, flow) in surface.cells.iter().zip(self.flows.iter()) {
+            let (Some(flow), Some(t)) = (flow, cell.channel_t(None)) else {
+                continue;
+            };
+            let age = surface.now.saturating_sub(t) as f32 / self.fit.max_age.max(1) as f32;
+            if age >= 1.0 {
+                continue;
+            }
+            let length = (flow.speed() * self.horizon as f64).min(1.5) * cell_size;
+            let direction = dvec3(flow.vx, -flow.vy, 0.0).normalize_or_zero();
+            for panel in surface.panels.iter() {
+                let fade = surface.window_fade(cell.channel_t(panel.channel));
+                if cell.channel_t(panel.channel).is_none() || fade <= 0.0 {
+                    continue;
+                }
+                let color = manim::WHITE.with_alpha((1.0 - age) * fade);
+                let center = cell.panel_center_of(panel, cell.x, cell.y);
+                let half = direction * length * panel.scale / 2.0;
+                arrows.extend(arrow(
+                    center - half,
+                    center + half,
+                    cell_size * panel.scale * 0.2,
+                    color,
+                ));
+            }
+        }
+        arrows
+    }
+}
+
+/// The time of the last corner of each cell, see [`TimeSurface::set_corner_detector`].
+#[derive(Clone)]
+pub(super) struct CornerLayer {
+    pub detector: Arc<dyn CornerDetector>,
+    /// How long the ring of a corner takes to expand and fade out, in event time
+    pub ring_duration: usize,
+    /// The count of the events flagged as corners
+    pub cnt: usize,
+    corner_ts: PersistentVec<Option<usize>>,
+}
+
+impl CornerLayer {
+    pub fn new(cell_cnt: usize, detector: Arc<dyn CornerDetector>) -> Self {
+        Self {
+            detector,
+            ring_duration: 100,
+            cnt: 0,
+            corner_ts: per_cell(cell_cnt, None),
+        }
+    }
+    pub fn record(&mut self, idx: usize, t: usize) {
+        *self.corner_ts.get_mut(idx).unwrap() = Some(t);
+        self.cnt += 1;
+    }
+    /// The rings of the corners that are recent enough, expanding from the cell and fading out
+    /// with their age and the window.
+    pub fn rings(&self, surface: &TimeSurface) -> Vec<VItem> {
+        let cell_size = surface.cell_size();
+        let duration = self.ring_duration.max(1) as f64;
+        let mut rings = vec![];
+        for (cell, corner_t) in surface.cells.iter().zip(self.corner_ts.iter()) {
+            let Some(progress) = corner_t
+                .map(|t| surface.now.saturating_sub(t) as f64 / duration)
+                .filter(|&progress| progress < 1.0)
+            else {
+                continue;
+            };
+            for panel in surface.panels.iter() {
+                let fade = surface.window_fade(cell.channel_t(panel.channel));
+                if cell.channel_t(panel.channel).is_none() || fade <= 0.0 {
+                    continue;
+                }
+                let color = manim::PURPLE_B.with_alpha((1.0 - progress as f32) * fade);
+                let radius = cell_size * panel.scale * (0.6 + progress);
+                rings.push(VItem::from(Circle::new(radius)).with(|ring| {
+                    ring.set_fill_color(color.with_alpha(0.0))
+                        .set_stroke_color(color)
+                        .set_stroke_width((cell_size * panel.scale * 0.08) as f32)
+                        .put_center_on(cell.panel_center_of(panel, cell.x, cell.y));
+                }));
+            }
+        }
+        rings
+    }
+}
+
+/// The cells drawn as isometric boxes, see [`TimeSurface::set_height_field`].
+#[derive(Debug, Clone, Copy)]
+pub(super) struct HeightField {
+    /// The height of a box at a value of 1.0, in cells
+    pub max_height: f64,
+}
+
+impl HeightField {
+    /// The boxes of the cells with the normalized `values` of each panel, painted from the
+    /// back to the front.
+    ///
+    /// Each panel is turned by 45 degrees and squashed to half its height, then each cell rises
+    /// by its height and its top and the two sides facing down are drawn.
+    pub fn boxes(&self, surface: &TimeSurface, values: &[Vec<f32>]) -> Vec<VItem> {
+        let max_height = self.max_height;
+        let center = surface.grid_center();
+        let mut boxes = vec![];
+        for (panel, values) in surface.panels.iter().zip(values) {
+            let cell_size = surface.cell_size() * panel.scale;
+            let half = cell_size * (1.0 - surface.style.padding_ratio) / 2.0;
+            let panel_center = center * panel.scale + panel.offset;
+            // Centered on the panel, boxes of full height included.
+            let base = panel_center - DVec3::Y * max_height * cell_size / 2.0;
+            let project = |offset: DVec3, height: f64| {
+                base + dvec3(
+                    (offset.x - offset.y) / 2.0,
+                    (offset.x + offset.y) / 4.0 + height,
+                    0.0,
+                )
+            };
+            let quad = |points: Vec<DVec3>, color: color::AlphaColor<color::Srgb>| {
+                VItem::from(Polygon::new(points)).with(|quad| {
+                    quad.set_fill_color(color)
+                        .set_stroke_color(manim::BLACK.with_alpha(0.5))
+                        .set_stroke_width((cell_size * 0.02) as f32);
+                })
+            };
+            // A larger `x + y` is further up the screen and behind.
+            let order = surface
+                .cells
+                .iter()
+                .enumerate()
+                .map(|(idx, cell)| {
+                    let offset = cell.panel_center_of(panel, cell.x, cell.y) - panel_center;
+                    (idx, offset)
+                })
+                .sorted_by(|(_, a), (_, b)| (b.x + b.y).total_cmp(&(a.x + a.y)));
+            for (idx, offset) in order {
+                let cell = &surface.cells[idx];
+                let t = cell.channel_t(panel.channel);
+                let value = values[idx] * surface.window_fade(t);
+                let [bottom_left, bottom_right, top_right, top_left] = [
+                    dvec3(-half, -half, 0.0),
+                    dvec3(half, -half, 0.0),
+                    dvec3(half, half, 0.0),
+                    dvec3(-half, half, 0.0),
+                ]
+                .map(|corner| offset + corner);
+                let corners = [bottom_left, bottom_right, top_right, top_left];
+                if t.is_none() || value <= 0.0 {
+                    let floor = corners.map(|corner| project(corner, 0.0)).to_vec();
+                    boxes.push(quad(floor, manim::GREY_E.with_alpha(0.5)));
+                    continue;
+                }
+                let height = value as f64 * max_height * cell_size;
+                let color = surface.cell_color(panel, cell, value).with_alpha(1.0);
+                let [r, g, b, _] = color.components;
+                for (from, to, shade) in [
+                    (bottom_left, bottom_right, 0.5),
+                    (top_left, bottom_left, 0.7),
+                ] {
+                    let side = vec![
+                        project(from, 0.0),
+                        project(to, 0.0),
+                        project(to, height),
+                        project(from, height),
+                    ];
+                    let color = color::AlphaColor::new([r * shade, g * shade, b * shade, 1.0]);
+                    boxes.push(quad(side, color));
+                }
+                let top = corners.map(|corner| project(corner, height)).to_vec();
+                boxes.push(quad(top, color));
+            }
+        }
+        boxes
+    }
+}
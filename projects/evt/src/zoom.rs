@@ -0,0 +1,316 @@
+//! Pausing on an event to show how the filter decides it.
+//!
+//! The camera zooms onto the cell of the event, the 3x3 neighbourhood is labeled with the
+//! differences `|t_i - t|` between the last time of each neighbour and the event, green for the
+//! neighbours that the filter of the surface counts as support and red for the others, and then
+//! the camera zooms back out.
+
+use ranim::{
+    animation::{fading::FadingAnim, transform::TransformAnim},
+    color::palettes::manim,
+    glam::{DVec3, dvec3},
+    items::{
+        ItemId,
+        vitem::{VItem, geometry::Square},
+    },
+    prelude::*,
+    render::primitives::{Extract, vitem::VItemPrimitive},
+};
+
+use crate::{
+    FRAME_SIZE, TimeSurface,
+    event::{Event, OutOfBounds},
+    filter::{TimestampMap, neighbours},
+    glyph::glyph_label,
+};
+
+/// The scene seconds of the camera moving in or out
+const ZOOM_SECS: f64 = 1.0;
+/// The scene seconds of the labels fading in or out
+const FADE_SECS: f64 = 0.5;
+
+/// The neighbourhood of an event on a [`TimeSurface`], with the time difference of each
+/// neighbour and the decision of the filter below it.
+#[derive(Debug, Clone)]
+pub struct NeighbourhoodOverlay {
+    /// The center of the cell of the event
+    pub center: DVec3,
+    pub cell_size: f64,
+    pub t: usize,
+    /// The offset of each neighbour within the sensor, with `|t_i - t|` if it has an event
+    pub diffs: Vec<((isize, isize), Option<usize>)>,
+    /// The offsets of the neighbours that support the event, see
+    /// [`EventFilter::supporters`](crate::filter::EventFilter::supporters)
+    pub supporters: Vec<(isize, isize)>,
+    pub accepted: bool,
+    /// The rule of the filter, see [`EventFilter::describe`](crate::filter::EventFilter::describe)
+    pub rule: Option<String>,
+    pub opacity: f32,
+}
+
+impl NeighbourhoodOverlay {
+    /// The neighbourhood of `event` on `surface`, which should be the state before the event
+    /// arrives, and the decision of the filter of the surface on it.
+    pub fn new(surface: &TimeSurface, event: &Event) -> Result<Self, OutOfBounds> {
+        surface.geometry().index(event)?;
+        let (t, x, y) = (event.t as usize, event.x as usize, event.y as usize);
+        // Without a panel for the polarity, like an OFF event on an ON surface, the cell of the
+        // unsplit grid is framed, which is already checked to be in the surface.
+        let (center, cell_size) = surface
+            .cell_rect(x, y, event.polarity)
+            .or_else(|| surface.grid_cell_rect(x, y))
+            .unwrap();
+        let offset =
+            |(nx, ny): (usize, usize)| (nx as isize - x as isize, ny as isize - y as isize);
+        let diffs = neighbours(surface, x, y, 1)
+            .map(|(nx, ny)| {
+                (
+                    offset((nx, ny)),
+                    surface.last_t(nx, ny).map(|nt| nt.abs_diff(t)),
+                )
+            })
+            .collect();
+        let filter = surface.filter();
+        let (accepted, supporters) = filter.decide(surface, t, x, y);
+        Ok(Self {
+            center,
+            cell_size,
+            t,
+            diffs,
+            supporters: supporters.into_iter().map(offset).collect(),
+            accepted,
+            rule: filter.describe(),
+            opacity: 1.0,
+        })
+    }
+    /// The camera that frames the neighbourhood and the decision below it, keeping the
+    /// orientation of `camera`.
+    pub fn camera(&self, camera: &CameraFrame) -> CameraFrame {
+        let [min, center, max] = self.get_bounding_box();
+        let mut camera = camera.clone();
+        camera.pos = dvec3(center.x, center.y, camera.pos.z);
+        camera.scale = (max.y - min.y) * 1.4 / FRAME_SIZE.y;
+        camera
+    }
+    fn label_size(&self) -> f64 {
+        self.cell_size * 0.18
+    }
+}
+
+impl BoundingBox for NeighbourhoodOverlay {
+    /// The box around the neighbourhood and the decision below it.
+    fn get_bounding_box(&self) -> [DVec3; 3] {
+        let half = self.cell_size * 1.5;
+        let bottom = self.center.y - half - self.label_size() * 3.0;
+        [
+            dvec3(self.center.x - half, bottom, self.center.z),
+            dvec3(
+                self.center.x,
+                (bottom + self.center.y + half) / 2.0,
+                self.center.z,
+            ),
+            dvec3(self.center.x + half, self.center.y + half, self.center.z),
+        ]
+    }
+}
+
+impl Shift for NeighbourhoodOverlay {
+    fn shift(&mut self, shift: DVec3) -> &mut Self {
+        self.center += shift;
+        self
+    }
+}
+
+impl Opacity for NeighbourhoodOverlay {
+    fn set_opacity(&mut self, opacity: f32) -> &mut Self {
+        self.opacity = opacity;
+        self
+    }
+}
+
+impl Interpolatable for NeighbourhoodOverlay {
+    /// Only the opacity changes, for fading in and out.
+    fn lerp(&self, target: &Self, t: f64) -> Self {
+        Self {
+            opacity: self.opacity.lerp(&target.opacity, t),
+            ..self.clone()
+        }
+    }
+}
+
+impl Extract for NeighbourhoodOverlay {
+    type Target = Vec<VItemPrimitive>;
+    fn extract(&self) -> Self::Target {
+        let size = self.cell_size;
+        let label_size = self.label_size();
+        let alpha = self.opacity;
+        // The labels sit in the lower part of the cells, below the labels of the surface.
+        let label_offset = dvec3(0.0, -size * 0.3, 0.0);
+        let outline = |center: DVec3, size: f64, color: color::AlphaColor<color::Srgb>| {
+            VItem::from(Square::new(size)).with(|square| {
+                square
+                    .set_fill_color(color.with_alpha(0.0))
+                    .set_stroke_color(color.with_alpha(alpha))
+                    .set_stroke_width((self.cell_size * 0.04) as f32)
+                    .put_center_on(center);
+            })
+        };
+        let label = |text: &str, center: DVec3, color: color::AlphaColor<color::Srgb>| {
+            glyph_label(text, label_size, center).with(|label| {
+                label.set_fill_color(color.with_alpha(alpha));
+            })
+        };
+
+        let mut items = vec![outline(self.center, size * 3.0, manim::WHITE)];
+        items.push(outline(self.center, size * 0.9, manim::YELLOW_C));
+        items.extend(label(
+            &self.t.to_string(),
+            self.center + label_offset,
+            manim::YELLOW_C,
+        ));
+        for &((dx, dy), diff) in &self.diffs {
+            let center = self.center + dvec3(dx as f64, -dy as f64, 0.0) * size;
+            let color = match diff {
+                _ if self.supporters.contains(&(dx, dy)) => manim::GREEN_C,
+                Some(_) => manim::RED_C,
+                None => manim::GREY_C,
+            };
+            items.push(outline(center, size * 0.9, color));
+            let text = diff.map_or("-".to_string(), |diff| diff.to_string());
+            items.extend(label(&text, center + label_offset, color));
+        }
+        let decision_y = self.center.y - size * 1.5 - label_size * 1.5;
+        let (decision, color) = match self.accepted {
+            true => ("accept", manim::GREEN_C),
+            false => ("reject", manim::RED_C),
+        };
+        let text = match &self.rule {
+            Some(rule) => format!("{decision}: {rule}"),
+            None => decision.to_string(),
+        };
+        items.extend(label(
+            &text,
+            dvec3(self.center.x, decision_y, self.center.z),
+            color,
+        ));
+        items.into_iter().map(|item| item.extract()).collect()
+    }
+}
+
+/// Pause the scene on `event`, zoom `r_cam` onto its neighbourhood on `surface`, show the
+/// [`NeighbourhoodOverlay`] for `hold_secs` and zoom back out.
+///
+/// `surface` should be the state before the event arrives. All timelines are synced before
+/// and after, so the other items stay still while the camera moves.
+pub fn zoom_on_event(
+    r: &mut RanimScene,
+    r_cam: &ItemId<CameraFrame>,
+    surface: &TimeSurface,
+    event: &Event,
+    hold_secs: f64,
+) -> Result<ItemId<NeighbourhoodOverlay>, OutOfBounds> {
+    let overlay = NeighbourhoodOverlay::new(surface, event)?;
+    r.timelines_mut().sync();
+
+    let camera = r.timeline(r_cam).snapshot();
+    let zoomed = overlay.camera(&camera);
+    let start_sec = r.timeline(r_cam).cur_sec();
+    r.timeline_mut(r_cam)
+        .play(
+            camera
+                .clone()
+                .transform_to(zoomed.clone())
+                .with_duration(ZOOM_SECS),
+        )
+        .forward(FADE_SECS * 2.0 + hold_secs)
+        .play(zoomed.transform_to(camera).with_duration(ZOOM_SECS));
+
+    let r_overlay = r.insert(overlay.clone());
+    r.timeline_mut(&r_overlay)
+        .forward_to(start_sec + ZOOM_SECS)
+        .play(overlay.clone().fade_in().with_duration(FADE_SECS))
+        .forward(hold_secs)
+        .play(overlay.fade_out().with_duration(FADE_SECS))
+        .hide();
+    r.timelines_mut().sync();
+    Ok(r_overlay)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        event::Polarity,
+        filter::{BackgroundActivityFilter, EventFilter, KnnFilter, RefractoryFilter},
+        time_surface::SurfaceMode,
+    };
+
+    #[test]
+    fn frames_an_event_of_a_hidden_polarity() {
+        let mut surface = TimeSurface::new(5, 5);
+        surface.set_mode(SurfaceMode::On);
+        surface.accept(&Event::new(10, 1, 2, Polarity::On)).unwrap();
+        let event = Event::new(30, 2, 2, Polarity::Off);
+        let overlay = NeighbourhoodOverlay::new(&surface, &event).unwrap();
+        assert_eq!(
+            Some((overlay.center, overlay.cell_size)),
+            surface.grid_cell_rect(2, 2)
+        );
+        assert_eq!(overlay.diffs.len(), 8);
+        assert!(overlay.diffs.contains(&((-1, 0), Some(20))));
+
+        let outside = Event::new(30, 5, 2, Polarity::Off);
+        assert!(NeighbourhoodOverlay::new(&surface, &outside).is_err());
+    }
+
+    #[test]
+    fn frames_the_panel_of_the_polarity() {
+        let mut surface = TimeSurface::new(5, 5);
+        surface.set_mode(SurfaceMode::SideBySide);
+        let event = Event::new(30, 2, 2, Polarity::Off);
+        let overlay = NeighbourhoodOverlay::new(&surface, &event).unwrap();
+        assert_eq!(
+            Some((overlay.center, overlay.cell_size)),
+            surface.cell_rect(2, 2, Polarity::Off)
+        );
+        assert!(overlay.center.x > 0.0);
+    }
+
+    #[test]
+    fn follows_the_filter_of_the_surface() {
+        let event = Event::new(30, 2, 2, Polarity::On);
+        let surface = |filter: Arc<dyn EventFilter>| {
+            let mut surface = TimeSurface::new(5, 5);
+            surface.accept(&Event::new(10, 1, 2, Polarity::On)).unwrap();
+            surface.accept(&Event::new(25, 3, 3, Polarity::On)).unwrap();
+            surface.accept(&Event::new(28, 2, 2, Polarity::On)).unwrap();
+            surface.set_filter(filter);
+            surface
+        };
+
+        let overlay = NeighbourhoodOverlay::new(
+            &surface(Arc::new(BackgroundActivityFilter::new(10))),
+            &event,
+        )
+        .unwrap();
+        assert_eq!(overlay.supporters, vec![(1, 1)]);
+        assert!(overlay.accepted);
+        assert_eq!(overlay.rule.as_deref(), Some("dt < 10"));
+
+        // Two supporters are needed, both neighbours are recent enough
+        let overlay =
+            NeighbourhoodOverlay::new(&surface(Arc::new(KnnFilter::new(2, 25))), &event).unwrap();
+        assert_eq!(overlay.supporters, vec![(-1, 0), (1, 1)]);
+        assert!(overlay.accepted);
+
+        // The refractory filter only looks at the pixel of the event
+        let overlay =
+            NeighbourhoodOverlay::new(&surface(Arc::new(RefractoryFilter::new(5))), &event)
+                .unwrap();
+        assert!(overlay.supporters.is_empty());
+        assert!(!overlay.accepted);
+        assert_eq!(overlay.rule.as_deref(), Some("own dt >= 5"));
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::PathBuf;
+
+use evt::{
+    event::{Event, Polarity},
+    reader::{Crop, EventFormat, ReadError, detect_format, parse_aedat4, parse_evt3, read_events},
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name)
+}
+
+fn on(t: u64, x: u16, y: u16) -> Event {
+    Event::new(t, x, y, Polarity::On)
+}
+
+fn off(t: u64, x: u16, y: u16) -> Event {
+    Event::new(t, x, y, Polarity::Off)
+}
+
+/// The events of the `evt2.raw`, `events.aedat4` and `events.csv` fixtures, up to an offset of t.
+fn expected(t0: u64) -> Vec<Event> {
+    vec![on(t0 + 100, 3, 4), off(t0 + 130, 5, 1), on(t0 + 200, 2, 7)]
+}
+
+#[test]
+fn detects_formats() {
+    let detect = |name| detect_format(&std::fs::read(fixture(name)).unwrap());
+    assert_eq!(detect("evt2.raw"), Some(EventFormat::Evt2));
+    assert_eq!(detect("evt3.raw"), Some(EventFormat::Evt3));
+    assert_eq!(detect("events.aedat4"), Some(EventFormat::Aedat4));
+    assert_eq!(detect("events.csv"), None);
+}
+
+#[test]
+fn reads_evt2() {
+    assert_eq!(read_events(fixture("evt2.raw")).unwrap(), expected(0));
+}
+
+#[test]
+fn reads_evt3() {
+    // An event on its own, then a `VECT_12` and a `VECT_8` continuing from the same base x.
+    assert_eq!(
+        read_events(fixture("evt3.raw")).unwrap(),
+        vec![
+            on(5000, 3, 4),
+            off(5100, 8, 6),
+            off(5100, 10, 6),
+            off(5100, 20, 6),
+        ]
+    );
+}
+
+#[test]
+fn reads_aedat4() {
+    // The packets of another stream and the trailing data table are skipped.
+    assert_eq!(
+        read_events(fixture("events.aedat4")).unwrap(),
+        expected(1_700_000_000_000_000)
+    );
+}
+
+#[test]
+fn reads_compressed_aedat4() {
+    // The packets of `events.aedat4`, each compressed on its own.
+    for name in ["lz4.aedat4", "zstd.aedat4"] {
+        assert_eq!(
+            read_events(fixture(name)).unwrap(),
+            expected(1_700_000_000_000_000),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn rejects_unknown_aedat4_compression() {
+    let err = read_events(fixture("unknown-compression.aedat4")).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ReadError::Unsupported {
+                format: EventFormat::Aedat4,
+                ..
+            }
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn rejects_negative_aedat4_coordinates() {
+    let err = read_events(fixture("negative.aedat4")).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ReadError::Malformed {
+                format: EventFormat::Aedat4,
+                ..
+            }
+        ),
+        "{err}"
+    );
+    assert!(err.to_string().contains("(-1, 1)"), "{err}");
+}
+
+#[test]
+fn drops_evt3_vectors_past_the_x_range() {
+    let words = [0x37f0u16, 0x4fff, 0x4fff]
+        .into_iter()
+        // Enough vectors without a new base x to overflow a u16
+        .chain(std::iter::repeat_n(0x50ff, 10_000))
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    let events = parse_evt3(&words);
+    let xs = events.iter().map(|event| event.x).collect::<Vec<_>>();
+    assert_eq!(xs, (0x7f0..=0x7ff).collect::<Vec<_>>());
+}
+
+fn assert_malformed_aedat4(result: Result<Vec<Event>, ReadError>) {
+    let err = result.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            ReadError::Malformed {
+                format: EventFormat::Aedat4,
+                ..
+            }
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn rejects_aedat4_event_counts_past_the_packet() {
+    // The element count of the first packet, followed by the timestamp of its first event
+    let mut data = std::fs::read(fixture("events.aedat4")).unwrap();
+    let mut pattern = 2u32.to_le_bytes().to_vec();
+    pattern.extend_from_slice(&1_700_000_000_000_100i64.to_le_bytes());
+    let pos = data
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .unwrap();
+    data[pos..pos + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_malformed_aedat4(parse_aedat4(&data));
+}
+
+#[test]
+fn rejects_negative_aedat4_timestamps() {
+    let mut data = std::fs::read(fixture("events.aedat4")).unwrap();
+    let t = 1_700_000_000_000_100i64.to_le_bytes();
+    let pos = data
+        .windows(t.len())
+        .position(|window| window == t)
+        .unwrap();
+    data[pos..pos + 8].copy_from_slice(&(-5i64).to_le_bytes());
+    assert_malformed_aedat4(parse_aedat4(&data));
+}
+
+#[test]
+fn rejects_aedat4_vtables_before_the_buffer() {
+    // A header whose root table points its vtable 100 bytes before the start
+    let mut data = b"#!AER-DAT4.0\r\n".to_vec();
+    data.extend_from_slice(&8i32.to_le_bytes());
+    data.extend_from_slice(&4u32.to_le_bytes());
+    data.extend_from_slice(&100i32.to_le_bytes());
+    assert_malformed_aedat4(parse_aedat4(&data));
+}
+
+#[test]
+fn reads_csv() {
+    // The header after the comment is skipped, the out of order row is sorted in.
+    assert_eq!(read_events(fixture("events.csv")).unwrap(), expected(0));
+}
+
+#[test]
+fn crops_events() {
+    let events = read_events(fixture("evt2.raw")).unwrap();
+    let cropped = Crop::new(2, 1, 4, 4).apply(events).collect::<Vec<_>>();
+    assert_eq!(cropped, vec![on(100, 1, 3), off(130, 3, 0)]);
+
+    let crop = Crop::centered(&expected(0), 2, 2);
+    assert_eq!(crop, Crop::new(2, 3, 2, 2));
+}
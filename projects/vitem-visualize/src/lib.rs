@@ -1,5 +1,6 @@
 use std::f64::consts::PI;
 
+use cached_extract::CachedExtract;
 use ranim::{
     animation::{creation::WritingAnim, fading::FadingAnim, transform::TransformAnim},
     color::palettes::manim,
@@ -185,6 +186,8 @@ fn vitem(r: &mut RanimScene) {
     let _r_texts = Group::<VItem>::from(text)
         .into_iter()
         .map(VisualVItem)
+        // The texts never change, so extract their visualization only once.
+        .map(CachedExtract::new)
         .map(|item| r.insert_and_show(item))
         .collect::<Vec<_>>();
     let default_cam = r.timeline(&r_cam).snapshot();